@@ -0,0 +1,1499 @@
+use std::sync::atomic::Ordering;
+
+use actix_web::http::StatusCode;
+use actix_web::middleware::from_fn;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::blob::SealedField;
+use crate::live;
+use crate::signature::SIGNED_COLUMNS;
+use crate::stats::Stats;
+use crate::{
+    content_hash, normalize_form, notify, request_id, request_timeout, respond, validate_form,
+    AppState, ContactForm,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+const MAX_IMPORT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Columns `GET /contacts?sort=` accepts; only these are ever put in SQL.
+const SORTABLE_COLUMNS: [&str; 3] = ["created_at", "name", "email"];
+
+/// Fields of a [`ContactSummary`] `GET /contacts?fields=` can select.
+const LIST_FIELDS: [&str; 17] = [
+    "id",
+    "name",
+    "email",
+    "subject",
+    "locale",
+    "source_page",
+    "website",
+    "site",
+    "created_at",
+    "handled_at",
+    "replied_at",
+    "reference",
+    "category",
+    "off_hours",
+    "email_hash",
+    "uuid",
+    "integrity",
+];
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(list_contacts))
+        .route("/emails", web::get().to(list_emails))
+        .route("/overdue", web::get().to(list_overdue))
+        .route("/stats", web::get().to(stats))
+        .route("/stream", web::get().to(stream))
+        .route("/{id}/handled", web::post().to(mark_handled))
+        .route("/{id}/spam-signals", web::get().to(spam_signals))
+        .route(
+            "/{id}/resend-notification",
+            web::post()
+                .to(resend_notification)
+                .wrap(from_fn(request_timeout::middleware)),
+        )
+        .route(
+            "/{id}/reply",
+            web::post()
+                .to(reply)
+                .wrap(from_fn(request_timeout::middleware)),
+        )
+        .service(
+            web::resource("/import")
+                .app_data(web::PayloadConfig::new(MAX_IMPORT_BYTES))
+                .route(web::post().to(import)),
+        );
+}
+
+pub fn maintenance_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/maintenance", web::get().to(maintenance_status))
+        .route("/maintenance", web::post().to(set_maintenance));
+}
+
+/// Checks the `Authorization: Bearer <token>` header against `--admin-token`.
+/// Without a configured token the admin API is disabled entirely.
+pub fn require_admin(req: &HttpRequest, data: &AppState) -> Result<(), HttpResponse> {
+    let Some(expected) = &data.admin_token else {
+        return Err(HttpResponse::NotFound().finish());
+    };
+
+    let provided = req
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    if constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(respond::error(
+            req,
+            StatusCode::UNAUTHORIZED,
+            serde_json::json!({"error": "Unauthorized"}),
+        ))
+    }
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Deserialize)]
+struct EmailsQuery {
+    #[serde(default)]
+    offset: u32,
+    limit: Option<u32>,
+    #[serde(default)]
+    min_count: u32,
+    #[serde(default)]
+    redacted: bool,
+    site: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ContactsQuery {
+    #[serde(default)]
+    offset: u32,
+    limit: Option<u32>,
+    sort: Option<String>,
+    /// Only submissions from this site, the origin host recorded on submit.
+    site: Option<String>,
+    /// Only submissions given this `--category-rules` category.
+    category: Option<String>,
+    /// Comma-separated [`LIST_FIELDS`] to return; all of them when unset.
+    fields: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ContactSummary {
+    id: i64,
+    name: String,
+    email: String,
+    subject: String,
+    locale: Option<String>,
+    source_page: Option<String>,
+    website: Option<String>,
+    site: Option<String>,
+    created_at: String,
+    handled_at: Option<String>,
+    replied_at: Option<String>,
+    reference: Option<String>,
+    category: Option<String>,
+    /// Stored outside `--business-hours` with `--off-hours flag`.
+    off_hours: bool,
+    /// With `--email-hash-mode`, for rows stored since it was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email_hash: Option<String>,
+    /// With `--use-uuid`, for rows stored since it was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+    /// With `--signing-key-file`: `ok`, `unsigned` or `tampered`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    integrity: Option<&'static str>,
+}
+
+/// Splits `?fields=` into [`LIST_FIELDS`], rejecting any other name.
+fn list_fields(fields: &str) -> Result<Vec<&'static str>, String> {
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            LIST_FIELDS
+                .iter()
+                .find(|known| **known == field)
+                .copied()
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown field: {}; expected one of {}",
+                        field,
+                        LIST_FIELDS.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Turns `column[:asc|:desc]` into an `ORDER BY` clause, accepting only
+/// [`SORTABLE_COLUMNS`]. Defaults to newest first.
+fn order_by(sort: Option<&str>) -> Result<String, String> {
+    let Some(sort) = sort else {
+        return Ok("created_at DESC, id DESC".to_string());
+    };
+    let (column, direction) = sort.split_once(':').unwrap_or((sort, "asc"));
+    let column = SORTABLE_COLUMNS
+        .iter()
+        .find(|known| **known == column)
+        .ok_or_else(|| {
+            format!(
+                "Unknown sort field: {}; expected one of {}",
+                column,
+                SORTABLE_COLUMNS.join(", ")
+            )
+        })?;
+    let direction = match direction {
+        "asc" => "ASC",
+        "desc" => "DESC",
+        _ => {
+            return Err(format!(
+                "Unknown sort direction: {}; expected asc or desc",
+                direction
+            ))
+        }
+    };
+    let collate = if *column == "created_at" {
+        ""
+    } else {
+        " COLLATE NOCASE"
+    };
+    Ok(format!(
+        "{}{} {}, id {}",
+        column, collate, direction, direction
+    ))
+}
+
+/// 400 for queries that need plaintext columns `--storage-mode=blob` leaves empty.
+fn unavailable_in_blob_mode(req: &HttpRequest, what: &str) -> HttpResponse {
+    respond::error(
+        req,
+        StatusCode::BAD_REQUEST,
+        serde_json::json!({
+            "error": format!("{} is unavailable with --storage-mode=blob", what),
+            "code": "unavailable_in_blob_mode",
+        }),
+    )
+}
+
+/// 400 for queries on a column `--encrypt-fields` stores encrypted.
+fn unavailable_for_encrypted(req: &HttpRequest, what: &str, field: &str) -> HttpResponse {
+    respond::error(
+        req,
+        StatusCode::BAD_REQUEST,
+        serde_json::json!({
+            "error": format!("{} is unavailable with --encrypt-fields={}", what, field),
+            "code": "unavailable_for_encrypted_field",
+        }),
+    )
+}
+
+fn sealed(data: &AppState, field: SealedField) -> bool {
+    data.sealed_fields
+        .as_ref()
+        .is_some_and(|fields| fields.seals(field))
+}
+
+/// Reads a text column, decrypting it when `--encrypt-fields` stored it
+/// encrypted.
+fn text(data: &AppState, row: &Row, column: usize, field: SealedField) -> rusqlite::Result<String> {
+    let stored: String = row.get(column)?;
+    match &data.sealed_fields {
+        Some(fields) => fields
+            .open(field, stored)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(column, Type::Text, e.into())),
+        None => Ok(stored),
+    }
+}
+
+/// Decrypts the `payload` in `column`, for rows stored in blob mode.
+fn reveal(data: &AppState, row: &Row, column: usize) -> rusqlite::Result<Option<ContactForm>> {
+    let Some(payload) = row.get::<_, Option<Vec<u8>>>(column)? else {
+        return Ok(None);
+    };
+    let opened = match &data.blob {
+        Some(cipher) => cipher.open(&payload),
+        None => Err("submission is encrypted; start with --storage-mode=blob".to_string()),
+    };
+    opened
+        .map(Some)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(column, Type::Blob, e.into()))
+}
+
+/// Parses an admin request body, answering malformed JSON or missing fields
+/// through [`respond::error`] like every other rejection.
+fn json_body<T: DeserializeOwned>(req: &HttpRequest, body: &[u8]) -> Result<T, HttpResponse> {
+    serde_json::from_slice(body).map_err(|e| {
+        respond::error(
+            req,
+            StatusCode::BAD_REQUEST,
+            serde_json::json!({
+                "error": format!("Invalid JSON: {}", e),
+                "code": "invalid_json",
+            }),
+        )
+    })
+}
+
+/// Individual submissions, newest first unless `?sort=` says otherwise,
+/// with only the `?fields=` asked for when given.
+async fn list_contacts(
+    req: HttpRequest,
+    query: web::Query<ContactsQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+
+    if data.blob.is_some() {
+        if query.site.is_some() {
+            return unavailable_in_blob_mode(&req, "Filtering by site");
+        }
+        if query
+            .sort
+            .as_deref()
+            .is_some_and(|sort| !sort.starts_with("created_at"))
+        {
+            return unavailable_in_blob_mode(&req, "Sorting by a submission field");
+        }
+    }
+    for (column, field) in [("name", SealedField::Name), ("email", SealedField::Email)] {
+        if sealed(&data, field)
+            && query
+                .sort
+                .as_deref()
+                .is_some_and(|sort| sort.split(':').next() == Some(column))
+        {
+            return unavailable_for_encrypted(&req, "Sorting by this field", column);
+        }
+    }
+
+    let order_by = match order_by(query.sort.as_deref()) {
+        Ok(order_by) => order_by,
+        Err(error) => {
+            return respond::error(
+                &req,
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({"error": error, "code": "invalid_sort"}),
+            )
+        }
+    };
+    let fields = match query.fields.as_deref().map(list_fields).transpose() {
+        Ok(fields) => fields,
+        Err(error) => {
+            return respond::error(
+                &req,
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({"error": error, "code": "invalid_fields"}),
+            )
+        }
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    // The signed columns include the message and attachment, so they are
+    // only read when `integrity` is wanted.
+    let signer = data.signer.as_ref().filter(|_| {
+        fields
+            .as_ref()
+            .is_none_or(|fields| fields.contains(&"integrity"))
+    });
+    let signed = match signer {
+        Some(_) => format!(", {}, signature", SIGNED_COLUMNS),
+        None => String::new(),
+    };
+    let db = data.db.lock().unwrap();
+    let result = db
+        .prepare(&format!(
+            "SELECT id, name, email, subject, source_page, site, created_at, handled_at, payload,
+                    replied_at, reference, category, off_hours, email_hash, uuid, website, locale{}
+             FROM contacts
+             WHERE (?3 IS NULL OR site = ?3) AND (?4 IS NULL OR category = ?4)
+             ORDER BY {}
+             LIMIT ?1 OFFSET ?2",
+            signed, order_by
+        ))
+        .and_then(|mut stmt| {
+            stmt.query_map(
+                params![limit, query.offset, query.site, query.category],
+                |row| {
+                    let mut summary = ContactSummary {
+                        id: row.get(0)?,
+                        name: text(&data, row, 1, SealedField::Name)?,
+                        email: text(&data, row, 2, SealedField::Email)?,
+                        subject: text(&data, row, 3, SealedField::Subject)?,
+                        locale: row.get(16)?,
+                        source_page: row.get(4)?,
+                        website: row.get(15)?,
+                        site: row.get(5)?,
+                        created_at: row.get(6)?,
+                        handled_at: row.get(7)?,
+                        replied_at: row.get(9)?,
+                        reference: row.get(10)?,
+                        category: row.get(11)?,
+                        off_hours: row.get(12)?,
+                        email_hash: row.get(13)?,
+                        uuid: row.get(14)?,
+                        integrity: match signer {
+                            Some(signer) => Some(signer.check(row, 17)?.as_str()),
+                            None => None,
+                        },
+                    };
+                    if let Some(form) = reveal(&data, row, 8)? {
+                        summary.name = form.name;
+                        summary.email = form.email;
+                        summary.subject = form.subject;
+                        summary.locale = form.locale;
+                        summary.source_page = form.source_page;
+                        summary.website = form.website;
+                    }
+                    Ok(summary)
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()
+        });
+
+    match result {
+        Ok(contacts) => {
+            let mut contacts = serde_json::to_value(contacts).unwrap_or_default();
+            if let (Some(fields), Some(contacts)) = (&fields, contacts.as_array_mut()) {
+                for contact in contacts.iter_mut().filter_map(|c| c.as_object_mut()) {
+                    contact.retain(|field, _| fields.contains(&field.as_str()));
+                }
+            }
+            respond::negotiated(
+                &req,
+                StatusCode::OK,
+                serde_json::json!({
+                    "contacts": contacts,
+                    "offset": query.offset,
+                    "limit": limit,
+                }),
+            )
+        }
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to load submissions"}),
+            )
+        }
+    }
+}
+
+/// The in-process counters since startup, plus processing time percentiles
+/// over every stored submission when `--track-processing-time` is on.
+async fn stats(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    stats: web::Data<Stats>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+
+    let mut summary = stats.summary();
+    if data.track_processing_time {
+        let db = data.db.lock().unwrap();
+        match processing_percentiles(&db) {
+            Ok(percentiles) => summary["processing_ms"] = percentiles,
+            Err(e) => {
+                eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+                return respond::error(
+                    &req,
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    serde_json::json!({"error": "Failed to load stats"}),
+                );
+            }
+        }
+    }
+    respond::negotiated(&req, StatusCode::OK, summary)
+}
+
+fn processing_percentiles(conn: &Connection) -> rusqlite::Result<serde_json::Value> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM contacts WHERE processing_ms IS NOT NULL",
+        [],
+        |row| row.get(0),
+    )?;
+    let percentile = |p: i64| -> rusqlite::Result<Option<i64>> {
+        if count == 0 {
+            return Ok(None);
+        }
+        conn.query_row(
+            "SELECT processing_ms FROM contacts WHERE processing_ms IS NOT NULL
+             ORDER BY processing_ms LIMIT 1 OFFSET ?1",
+            // Nearest rank: the smallest value with at least p% at or below it.
+            params![(count * p + 99) / 100 - 1],
+            |row| row.get(0),
+        )
+        .optional()
+    };
+    Ok(serde_json::json!({
+        "count": count,
+        "p50": percentile(50)?,
+        "p95": percentile(95)?,
+        "max": percentile(100)?,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ReportQuery {
+    /// Mask the `--redact-fields` columns, for reports shared beyond admins.
+    #[serde(default)]
+    redacted: bool,
+    site: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EmailSummary {
+    email: String,
+    count: i64,
+    last_submitted_at: String,
+}
+
+async fn list_emails(
+    req: HttpRequest,
+    query: web::Query<EmailsQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+
+    if data.blob.is_some() {
+        return unavailable_in_blob_mode(&req, "Grouping by email");
+    }
+    if sealed(&data, SealedField::Email) {
+        return unavailable_for_encrypted(&req, "Grouping by email", "email");
+    }
+    if data
+        .email_hasher
+        .as_ref()
+        .is_some_and(|hasher| hasher.withholds_email())
+    {
+        return respond::error(
+            &req,
+            StatusCode::BAD_REQUEST,
+            serde_json::json!({
+                "error": "Grouping by email is unavailable with --email-hash-mode=hash-only; \
+                          count distinct email_hash values instead",
+                "code": "unavailable_for_hashed_email",
+            }),
+        );
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let db = data.db.lock().unwrap();
+    let result = db
+        .prepare(
+            "SELECT email, COUNT(*), MAX(created_at) FROM contacts
+             WHERE ?4 IS NULL OR site = ?4
+             GROUP BY email COLLATE NOCASE
+             HAVING COUNT(*) >= ?1
+             ORDER BY MAX(created_at) DESC
+             LIMIT ?2 OFFSET ?3",
+        )
+        .and_then(|mut stmt| {
+            let params = params![query.min_count, limit, query.offset, query.site];
+            stmt.query_map(params, |row| {
+                Ok(EmailSummary {
+                    email: row.get(0)?,
+                    count: row.get(1)?,
+                    last_submitted_at: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+        });
+
+    match result {
+        Ok(mut emails) => {
+            if query.redacted {
+                for summary in &mut emails {
+                    summary.email = data.redaction.field("email", &summary.email);
+                }
+            }
+            respond::negotiated(
+                &req,
+                StatusCode::OK,
+                serde_json::json!({
+                    "emails": emails,
+                    "offset": query.offset,
+                    "limit": limit,
+                }),
+            )
+        }
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to load email addresses"}),
+            )
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OverdueContact {
+    id: i64,
+    name: String,
+    email: String,
+    subject: String,
+    source_page: Option<String>,
+    site: Option<String>,
+    created_at: String,
+    age_hours: f64,
+}
+
+/// Unhandled submissions older than `--sla-hours`, oldest first.
+async fn list_overdue(
+    req: HttpRequest,
+    query: web::Query<ReportQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+
+    let Some(sla_hours) = data.sla_hours else {
+        return respond::error(
+            &req,
+            StatusCode::NOT_FOUND,
+            serde_json::json!({"error": "No SLA configured"}),
+        );
+    };
+    if data.blob.is_some() && query.site.is_some() {
+        return unavailable_in_blob_mode(&req, "Filtering by site");
+    }
+
+    let db = data.db.lock().unwrap();
+    let result = db
+        .prepare(
+            "SELECT id, name, email, subject, source_page, site, created_at,
+                    ROUND((julianday(?3) - julianday(created_at)) * 24, 1) AS age_hours,
+                    payload
+             FROM contacts
+             WHERE handled_at IS NULL AND created_at < datetime(?3, ?1)
+               AND (?2 IS NULL OR site = ?2)
+             ORDER BY created_at ASC",
+        )
+        .and_then(|mut stmt| {
+            let cutoff = format!("-{} hours", sla_hours);
+            let now = data.clock.sql_now();
+            stmt.query_map(params![cutoff, query.site, now], |row| {
+                let mut contact = OverdueContact {
+                    id: row.get(0)?,
+                    name: text(&data, row, 1, SealedField::Name)?,
+                    email: text(&data, row, 2, SealedField::Email)?,
+                    subject: text(&data, row, 3, SealedField::Subject)?,
+                    source_page: row.get(4)?,
+                    site: row.get(5)?,
+                    created_at: row.get(6)?,
+                    age_hours: row.get(7)?,
+                };
+                if let Some(form) = reveal(&data, row, 8)? {
+                    contact.name = form.name;
+                    contact.email = form.email;
+                    contact.subject = form.subject;
+                    contact.source_page = form.source_page;
+                }
+                Ok(contact)
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+        });
+
+    match result {
+        Ok(mut contacts) => {
+            if query.redacted {
+                for contact in &mut contacts {
+                    contact.name = data.redaction.field("name", &contact.name);
+                    contact.email = data.redaction.field("email", &contact.email);
+                    contact.subject = data.redaction.field("subject", &contact.subject);
+                }
+            }
+            respond::negotiated(
+                &req,
+                StatusCode::OK,
+                serde_json::json!({
+                    "sla_hours": sla_hours,
+                    "overdue": contacts,
+                }),
+            )
+        }
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to load overdue submissions"}),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StreamQuery {
+    /// Stored submissions sent before the live ones, oldest first.
+    recent: Option<u32>,
+}
+
+/// A submission as `GET /contacts/stream` sends it.
+pub fn streamed(
+    id: i64,
+    form: &ContactForm,
+    locale: Option<&str>,
+    site: Option<&str>,
+    category: Option<&str>,
+    created_at: &str,
+    reference: Option<&str>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "name": form.name,
+        "email": form.email,
+        "subject": form.subject,
+        "message": form.message,
+        "locale": locale,
+        "source_page": form.source_page,
+        "website": form.website,
+        "site": site,
+        "category": category,
+        "created_at": created_at,
+        "reference": reference,
+    })
+}
+
+/// Server-Sent Events for a live dashboard: the `?recent=` (default 20)
+/// latest submissions, then each new one as it is stored, with a heartbeat
+/// comment every 15 seconds while it is quiet. Submissions imported or
+/// stored by another process are not streamed.
+async fn stream(
+    req: HttpRequest,
+    query: web::Query<StreamQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+
+    let recent = query.recent.unwrap_or(20).min(MAX_PAGE_SIZE);
+    let backlog = {
+        let db = data.db.lock().unwrap();
+        db.prepare(
+            "SELECT id, name, email, subject, message, source_page, site, category, created_at,
+                    reference, payload, website, locale
+             FROM contacts ORDER BY id DESC LIMIT ?1",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map(params![recent], |row| {
+                let id = row.get(0)?;
+                let form = ContactForm {
+                    name: text(&data, row, 1, SealedField::Name)?,
+                    email: text(&data, row, 2, SealedField::Email)?,
+                    subject: text(&data, row, 3, SealedField::Subject)?,
+                    message: text(&data, row, 4, SealedField::Message)?,
+                    source_page: row.get(5)?,
+                    locale: row.get(12)?,
+                    website: row.get(11)?,
+                    ..Default::default()
+                };
+                let form = reveal(&data, row, 10)?.unwrap_or(form);
+                let submission = streamed(
+                    id,
+                    &form,
+                    form.locale.as_deref(),
+                    row.get::<_, Option<String>>(6)?.as_deref(),
+                    row.get::<_, Option<String>>(7)?.as_deref(),
+                    &row.get::<_, String>(8)?,
+                    row.get::<_, Option<String>>(9)?.as_deref(),
+                );
+                Ok(live::event(id, &submission))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+        })
+    };
+
+    match backlog {
+        Ok(mut backlog) => {
+            backlog.reverse();
+            HttpResponse::Ok()
+                .content_type("text/event-stream")
+                .insert_header(("Cache-Control", "no-cache"))
+                .insert_header(("X-Accel-Buffering", "no"))
+                .body(data.live.subscribe(backlog))
+        }
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to load submissions"}),
+            )
+        }
+    }
+}
+
+/// The submission a `/{id}` path segment names: its integer id, or the
+/// `uuid` it was given with `--use-uuid`.
+fn submission_id(req: &HttpRequest, data: &AppState, segment: &str) -> Result<i64, HttpResponse> {
+    if let Ok(id) = segment.parse() {
+        return Ok(id);
+    }
+    let found = data
+        .db
+        .lock()
+        .unwrap()
+        .query_row(
+            "SELECT id FROM contacts WHERE uuid = ?1",
+            params![segment],
+            |row| row.get(0),
+        )
+        .optional();
+    match found {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(respond::error(
+            req,
+            StatusCode::NOT_FOUND,
+            serde_json::json!({"error": "Submission not found"}),
+        )),
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(req), e);
+            Err(respond::error(
+                req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to load submission"}),
+            ))
+        }
+    }
+}
+
+/// Marks a submission as handled so it drops out of the overdue report.
+async fn mark_handled(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+    let id = match submission_id(&req, &data, &path) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let db = data.db.lock().unwrap();
+    let result = db.execute(
+        "UPDATE contacts SET handled_at = COALESCE(handled_at, ?2) WHERE id = ?1",
+        params![id, data.clock.sql_now()],
+    );
+
+    match result {
+        Ok(0) => respond::error(
+            &req,
+            StatusCode::NOT_FOUND,
+            serde_json::json!({"error": "Submission not found"}),
+        ),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to update submission"}),
+            )
+        }
+    }
+}
+
+/// The anti-spam signals recorded when a submission was accepted.
+async fn spam_signals(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+
+    let id = match submission_id(&req, &data, &path) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let db = data.db.lock().unwrap();
+    let result = db
+        .query_row(
+            "SELECT spam_signals FROM contacts WHERE id = ?1",
+            params![id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional();
+
+    match result {
+        Ok(Some(signals)) => respond::negotiated(
+            &req,
+            StatusCode::OK,
+            serde_json::json!({
+                "id": id,
+                "signals": signals.and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok()),
+            }),
+        ),
+        Ok(None) => respond::error(
+            &req,
+            StatusCode::NOT_FOUND,
+            serde_json::json!({"error": "Submission not found"}),
+        ),
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to load spam signals"}),
+            )
+        }
+    }
+}
+
+/// Runs the configured notification channels again for a stored submission,
+/// e.g. after the SMTP server or webhook was down, reporting each channel's
+/// outcome.
+async fn resend_notification(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+
+    let configured = data.webhook.is_some()
+        || data.webhook_batch.is_some()
+        || data.email.is_some()
+        || data.socket_sink.is_some();
+    if !configured {
+        return respond::error(
+            &req,
+            StatusCode::CONFLICT,
+            serde_json::json!({
+                "error": "No notification channels are configured",
+                "code": "no_notification_channels",
+            }),
+        );
+    }
+    if data.test_mode {
+        return respond::error(
+            &req,
+            StatusCode::CONFLICT,
+            serde_json::json!({
+                "error": "Notifications are skipped in test mode",
+                "code": "test_mode",
+            }),
+        );
+    }
+
+    let id = match submission_id(&req, &data, &path) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let stored = {
+        let db = data.db.lock().unwrap();
+        db.query_row(
+            "SELECT name, email, subject, message, locale, source_page, site, payload, website
+             FROM contacts WHERE id = ?1",
+            params![id],
+            |row| {
+                let form = ContactForm {
+                    name: text(&data, row, 0, SealedField::Name)?,
+                    email: text(&data, row, 1, SealedField::Email)?,
+                    subject: text(&data, row, 2, SealedField::Subject)?,
+                    message: text(&data, row, 3, SealedField::Message)?,
+                    locale: row.get(4)?,
+                    source_page: row.get(5)?,
+                    website: row.get(8)?,
+                    ..Default::default()
+                };
+                let form = reveal(&data, row, 7)?.unwrap_or(form);
+                Ok((form, row.get::<_, Option<String>>(6)?))
+            },
+        )
+        .optional()
+    };
+
+    match stored {
+        Ok(Some((form, site))) => {
+            let request_id = request_id::get(&req);
+            let deliveries = notify(&data, id, site.as_deref(), &form, &request_id).await;
+            let channels: serde_json::Map<String, serde_json::Value> = deliveries
+                .iter()
+                .map(|(channel, delivery)| (channel.to_string(), delivery.summary()))
+                .collect();
+            respond::json(
+                &req,
+                StatusCode::OK,
+                serde_json::json!({"id": id, "channels": channels}),
+            )
+        }
+        Ok(None) => respond::error(
+            &req,
+            StatusCode::NOT_FOUND,
+            serde_json::json!({"error": "Submission not found"}),
+        ),
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to load submission"}),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReplyRequest {
+    message: String,
+}
+
+/// Emails `message` to the submitter as a reply threaded under their
+/// submission, records it in `replies` and marks the submission replied and
+/// handled. Nothing is recorded when sending fails.
+async fn reply(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Bytes,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+    let body: ReplyRequest = match json_body(&req, &body) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let Some(email) = &data.email else {
+        return respond::error(
+            &req,
+            StatusCode::CONFLICT,
+            serde_json::json!({
+                "error": "Replies are sent by email; configure --smtp-url",
+                "code": "email_not_configured",
+            }),
+        );
+    };
+    if data.test_mode {
+        return respond::error(
+            &req,
+            StatusCode::CONFLICT,
+            serde_json::json!({
+                "error": "Emails are not sent in test mode",
+                "code": "test_mode",
+            }),
+        );
+    }
+    if body.message.trim().is_empty() {
+        return respond::error(
+            &req,
+            StatusCode::BAD_REQUEST,
+            serde_json::json!({
+                "error": "Reply message must not be empty",
+                "code": "empty_reply",
+            }),
+        );
+    }
+
+    let id = match submission_id(&req, &data, &path) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let stored = {
+        let db = data.db.lock().unwrap();
+        db.query_row(
+            "SELECT email, subject, payload, uuid FROM contacts WHERE id = ?1",
+            params![id],
+            |row| {
+                let form = ContactForm {
+                    email: text(&data, row, 0, SealedField::Email)?,
+                    subject: text(&data, row, 1, SealedField::Subject)?,
+                    ..Default::default()
+                };
+                let form = reveal(&data, row, 2)?.unwrap_or(form);
+                Ok((form, row.get::<_, Option<String>>(3)?))
+            },
+        )
+        .optional()
+        .and_then(|stored| {
+            let earlier = db
+                .prepare("SELECT message_id FROM replies WHERE contact_id = ?1 ORDER BY id")?
+                .query_map(params![id], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(stored.map(|(form, uuid)| (form, uuid, earlier)))
+        })
+    };
+    let (form, uuid, earlier) = match stored {
+        Ok(Some(stored)) => stored,
+        Ok(None) => {
+            return respond::error(
+                &req,
+                StatusCode::NOT_FOUND,
+                serde_json::json!({"error": "Submission not found"}),
+            )
+        }
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            return respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to load submission"}),
+            );
+        }
+    };
+    if form.email.is_empty() {
+        return respond::error(
+            &req,
+            StatusCode::CONFLICT,
+            serde_json::json!({
+                "error": "The address was not stored with --email-hash-mode=hash-only",
+                "code": "email_not_stored",
+            }),
+        );
+    }
+
+    let (message, payload) = match &data.blob {
+        Some(cipher) => match cipher.seal_text(&body.message) {
+            Ok(payload) => (String::new(), Some(payload)),
+            Err(e) => {
+                eprintln!("[{}] Reply encryption error: {}", request_id::get(&req), e);
+                return respond::error(
+                    &req,
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    serde_json::json!({"error": "Failed to encrypt the reply"}),
+                );
+            }
+        },
+        None => (body.message.clone(), None),
+    };
+
+    let sent = email
+        .reply(
+            &uuid.unwrap_or_else(|| id.to_string()),
+            &form.email,
+            &form.subject,
+            body.message.clone(),
+            &earlier,
+        )
+        .await;
+    let message_id = match sent {
+        Ok(message_id) => message_id,
+        Err(e) => {
+            eprintln!("[{}] Reply email error: {}", request_id::get(&req), e);
+            return respond::error(
+                &req,
+                StatusCode::BAD_GATEWAY,
+                serde_json::json!({
+                    "error": "Failed to send the reply",
+                    "code": "reply_failed",
+                }),
+            );
+        }
+    };
+
+    let sent_at = data.clock.sql_now();
+    let recorded = {
+        let db = data.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO replies (contact_id, message, payload, message_id, sent_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, message, payload, message_id, sent_at],
+        )
+        .and_then(|_| {
+            let reply_id = db.last_insert_rowid();
+            db.execute(
+                "UPDATE contacts SET replied_at = ?2, handled_at = COALESCE(handled_at, ?2)
+                 WHERE id = ?1",
+                params![id, sent_at],
+            )?;
+            Ok(reply_id)
+        })
+    };
+
+    match recorded {
+        Ok(reply_id) => respond::json(
+            &req,
+            StatusCode::CREATED,
+            serde_json::json!({
+                "id": reply_id,
+                "contact_id": id,
+                "message_id": message_id,
+                "sent_at": sent_at,
+            }),
+        ),
+        Err(e) => {
+            eprintln!(
+                "[{}] Reply {} to submission {} was sent but not recorded: {}",
+                request_id::get(&req),
+                message_id,
+                id,
+                e
+            );
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Reply was sent but could not be recorded"}),
+            )
+        }
+    }
+}
+
+/// One historical submission; `id` and `created_at` are kept when given.
+#[derive(Deserialize)]
+struct ImportRecord {
+    id: Option<i64>,
+    created_at: Option<String>,
+    #[serde(flatten)]
+    form: ContactForm,
+}
+
+/// Imports a JSON array or JSON-lines stream of submissions in one
+/// transaction, validating each record like a live submission and reporting
+/// the outcome of every record. Records that fail are left out; the rest are
+/// still committed.
+async fn import(req: HttpRequest, body: web::Bytes, data: web::Data<AppState>) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+
+    let text = String::from_utf8_lossy(&body);
+    let records: Vec<Result<ImportRecord, String>> = if text.trim_start().starts_with('[') {
+        match serde_json::from_str::<Vec<serde_json::Value>>(&text) {
+            Ok(values) => values
+                .into_iter()
+                .map(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+                .collect(),
+            Err(e) => {
+                return respond::error(
+                    &req,
+                    StatusCode::BAD_REQUEST,
+                    serde_json::json!({
+                        "error": format!("Invalid JSON: {}", e),
+                        "code": "invalid_json",
+                    }),
+                )
+            }
+        }
+    } else {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| e.to_string()))
+            .collect()
+    };
+
+    let mut db = data.db.lock().unwrap();
+    let outcome = db.transaction().and_then(|tx| {
+        let mut results = Vec::with_capacity(records.len());
+        for (index, record) in records.into_iter().enumerate() {
+            let mut result = match record {
+                Ok(record) => import_record(&tx, record, &data)?,
+                Err(e) => serde_json::json!({
+                    "status": "failed",
+                    "code": "invalid_json",
+                    "error": e,
+                }),
+            };
+            result["index"] = index.into();
+            results.push(result);
+        }
+        tx.commit()?;
+        Ok(results)
+    });
+
+    match outcome {
+        Ok(results) => {
+            let count = |status: &str| results.iter().filter(|r| r["status"] == status).count();
+            respond::json(
+                &req,
+                StatusCode::OK,
+                serde_json::json!({
+                    "imported": count("imported"),
+                    "skipped": count("skipped"),
+                    "failed": count("failed"),
+                    "results": results,
+                }),
+            )
+        }
+        Err(e) => {
+            eprintln!("[{}] Database error: {}", request_id::get(&req), e);
+            respond::error(
+                &req,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": "Failed to import submissions"}),
+            )
+        }
+    }
+}
+
+/// Inserts one record, turning per-record problems into a `failed` result
+/// and only returning `Err` for errors that should abort the import.
+fn import_record(
+    conn: &Connection,
+    record: ImportRecord,
+    data: &AppState,
+) -> rusqlite::Result<serde_json::Value> {
+    let mut record = record;
+    normalize_form(&mut record.form, &data.validation);
+
+    let failed = |code: &str, error: String| {
+        Ok(serde_json::json!({"status": "failed", "code": code, "error": error}))
+    };
+
+    if let Err(error) = validate_form(&record.form, &data.validation) {
+        return failed(error.code(), error.to_string());
+    }
+
+    let created_at: Option<String> = match &record.created_at {
+        Some(raw) => match conn.query_row("SELECT datetime(?1)", params![raw], |row| row.get(0))? {
+            Some(created_at) => Some(created_at),
+            None => return failed("invalid_format", format!("Invalid created_at: {}", raw)),
+        },
+        None => None,
+    };
+
+    if data.import_skip_duplicates && is_duplicate(conn, &record, created_at.as_deref(), data)? {
+        return Ok(serde_json::json!({"status": "skipped", "id": record.id}));
+    }
+
+    let hash = data.blob.is_none().then(|| content_hash(&record.form));
+    let category = data
+        .categorizer
+        .as_ref()
+        .map(|rules| rules.categorize(&record.form.subject, &record.form.message));
+    let email_hash = data
+        .email_hasher
+        .as_ref()
+        .map(|hasher| hasher.hash(&record.form.email));
+    let uuid = data
+        .uuid_version
+        .map(|version| version.generate(data.clock.now()));
+    if data
+        .email_hasher
+        .as_ref()
+        .is_some_and(|hasher| hasher.withholds_email())
+    {
+        record.form.email.clear();
+    }
+    // In blob mode only the encrypted payload carries the fields.
+    let (form, payload) = match (&data.blob, &data.sealed_fields) {
+        (Some(cipher), _) => match cipher.seal(&record.form) {
+            Ok(payload) => (ContactForm::default(), Some(payload)),
+            Err(e) => return failed("encryption_failed", e),
+        },
+        (None, Some(fields)) => match fields.seal(&record.form) {
+            Ok(form) => (form, None),
+            Err(e) => return failed("encryption_failed", e),
+        },
+        (None, None) => (record.form, None),
+    };
+    let inserted = conn.execute(
+        "INSERT INTO contacts
+            (id, name, email, subject, message, locale, source_page, content_hash, created_at,
+             payload, category, email_hash, uuid, website)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, COALESCE(?9, ?11), ?10, ?12, ?13, ?14, ?15)",
+        params![
+            record.id,
+            form.name,
+            form.email,
+            form.subject,
+            form.message,
+            form.locale,
+            form.source_page,
+            hash,
+            created_at,
+            payload,
+            data.clock.sql_now(),
+            category,
+            email_hash,
+            uuid,
+            form.website,
+        ],
+    );
+
+    match inserted {
+        Ok(_) => {
+            let id = conn.last_insert_rowid();
+            if let Some(signer) = &data.signer {
+                signer.sign_stored(conn, id)?;
+            }
+            Ok(serde_json::json!({"status": "imported", "id": id}))
+        }
+        Err(rusqlite::Error::SqliteFailure(e, message))
+            if e.code == ErrorCode::ConstraintViolation =>
+        {
+            let constraint = message.unwrap_or_else(|| e.to_string());
+            failed(
+                "conflict",
+                match record.id {
+                    Some(id) => format!("Record with id {} conflicts: {}", id, constraint),
+                    None => format!("Record conflicts: {}", constraint),
+                },
+            )
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether `record` was imported before: its id is taken, or a submission
+/// created at the same time has the same email and message. Stored rows are
+/// compared as read back, since the email may be encrypted or withheld under
+/// `--email-hash` and older rows lack a `content_hash`.
+fn is_duplicate(
+    conn: &Connection,
+    record: &ImportRecord,
+    created_at: Option<&str>,
+    data: &AppState,
+) -> rusqlite::Result<bool> {
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM contacts WHERE id = ?1)",
+        params![record.id],
+        |row| row.get(0),
+    )?;
+    let Some(created_at) = created_at.filter(|_| !taken) else {
+        return Ok(taken);
+    };
+
+    let email_hash = data
+        .email_hasher
+        .as_ref()
+        .map(|hasher| hasher.hash(&record.form.email));
+    let mut stmt = conn.prepare(
+        "SELECT email, message, email_hash, payload FROM contacts WHERE created_at = ?1",
+    )?;
+    let mut rows = stmt.query(params![created_at])?;
+    while let Some(row) = rows.next()? {
+        // Rows the current keys cannot open are not compared.
+        let stored = match reveal(data, row, 3) {
+            Ok(Some(form)) => Some((form.email, form.message)),
+            Ok(None) => text(data, row, 0, SealedField::Email)
+                .and_then(|email| Ok((email, text(data, row, 1, SealedField::Message)?)))
+                .ok(),
+            Err(_) => None,
+        };
+        let Some((email, message)) = stored else {
+            continue;
+        };
+        let stored_hash: Option<String> = row.get(2)?;
+        let same_email = match (&email_hash, stored_hash) {
+            (Some(hash), Some(stored_hash)) => *hash == stored_hash,
+            _ => email.eq_ignore_ascii_case(&record.form.email),
+        };
+        if same_email && message.trim() == record.form.message.trim() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[derive(Deserialize)]
+struct MaintenanceRequest {
+    enabled: bool,
+}
+
+async fn maintenance_status(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+    respond::json(
+        &req,
+        StatusCode::OK,
+        serde_json::json!({"enabled": data.maintenance.load(Ordering::Acquire)}),
+    )
+}
+
+/// Pauses or resumes intake; only the submission routes are affected.
+async fn set_maintenance(
+    req: HttpRequest,
+    body: web::Bytes,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = require_admin(&req, &data) {
+        return response;
+    }
+    let body: MaintenanceRequest = match json_body(&req, &body) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    data.maintenance.store(body.enabled, Ordering::Release);
+    println!(
+        "[{}] {}",
+        request_id::get(&req),
+        serde_json::json!({"event": "maintenance", "enabled": body.enabled})
+    );
+    respond::json(
+        &req,
+        StatusCode::OK,
+        serde_json::json!({"enabled": body.enabled}),
+    )
+}
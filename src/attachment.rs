@@ -0,0 +1,58 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// MIME types recognised by [`sniff`], the default `--attachment-types`.
+pub const KNOWN_TYPES: [&str; 5] = [
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+];
+
+/// A decoded attachment and the type detected from its contents.
+pub struct Attachment {
+    pub mime: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// As base64, for `--spool-file`; the type is sniffed again when read back.
+impl Serialize for Attachment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(&self.bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for Attachment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = decode(&encoded).ok_or_else(|| de::Error::custom("invalid base64"))?;
+        let mime = sniff(&bytes).ok_or_else(|| de::Error::custom("unknown attachment type"))?;
+        Ok(Attachment { mime, bytes })
+    }
+}
+
+/// Decodes standard base64, tolerating a `data:<type>;base64,` prefix as
+/// produced by `FileReader.readAsDataURL`. The declared type is ignored in
+/// favour of [`sniff`].
+pub fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = match encoded.split_once(";base64,") {
+        Some((prefix, data)) if prefix.starts_with("data:") => data,
+        _ => encoded,
+    };
+    STANDARD.decode(encoded.trim()).ok()
+}
+
+/// Detects the MIME type from the file's magic bytes rather than trusting
+/// anything the client claims.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => Some("image/png"),
+        [0xff, 0xd8, 0xff, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'%', b'P', b'D', b'F', b'-', ..] => Some("application/pdf"),
+        _ => None,
+    }
+}
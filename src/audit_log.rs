@@ -0,0 +1,138 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::ContactForm;
+
+/// Hash of the (nonexistent) entry before the first one.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Append-only, hash-chained JSON-lines log of accepted submissions.
+///
+/// Every entry records the hash of the entry before it, so editing, removing
+/// or reordering any line breaks the chain from that point on. This only makes
+/// tampering detectable: it is never rotated or compacted, and it is not a
+/// replacement for backups of the database.
+pub struct AuditLog {
+    chain: Mutex<Chain>,
+}
+
+struct Chain {
+    file: File,
+    seq: u64,
+    last_hash: String,
+}
+
+/// The first entry that does not follow from the ones before it.
+#[derive(Debug)]
+pub struct BrokenLink {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl AuditLog {
+    /// Opens `path` for appending, continuing the chain from its last entry.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let (seq, last_hash) = match verify(path)? {
+            Ok(tail) => tail,
+            Err(broken) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: chain broken at line {}: {}",
+                        path.display(),
+                        broken.line,
+                        broken.reason
+                    ),
+                ))
+            }
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog {
+            chain: Mutex::new(Chain {
+                file,
+                seq,
+                last_hash,
+            }),
+        })
+    }
+
+    pub fn append(&self, form: &ContactForm) -> io::Result<()> {
+        let mut chain = self.chain.lock().unwrap();
+        let timestamp = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+
+        let mut entry = serde_json::json!({
+            "seq": chain.seq + 1,
+            "timestamp": timestamp,
+            "prev": chain.last_hash,
+            "submission": form,
+        });
+        let hash = entry_hash(&entry);
+        entry["hash"] = hash.clone().into();
+
+        chain.file.write_all(format!("{}\n", entry).as_bytes())?;
+        chain.file.sync_data()?;
+        chain.seq += 1;
+        chain.last_hash = hash;
+        Ok(())
+    }
+}
+
+/// Walks the chain in `path`, returning the last sequence number and hash, or
+/// the first broken link. A missing file is an empty chain.
+pub fn verify(path: &Path) -> io::Result<Result<(u64, String), BrokenLink>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Ok((0, GENESIS.to_string()))),
+        Err(e) => return Err(e),
+    };
+
+    let mut seq = 0;
+    let mut last_hash = GENESIS.to_string();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let broken = |reason: &str| {
+            Ok(Err(BrokenLink {
+                line: index + 1,
+                reason: reason.to_string(),
+            }))
+        };
+
+        let Ok(mut entry) = serde_json::from_str::<Value>(&line) else {
+            return broken("not a JSON entry");
+        };
+        let Some(Value::String(hash)) = entry.as_object_mut().and_then(|e| e.remove("hash")) else {
+            return broken("missing hash");
+        };
+        if entry["prev"] != last_hash.as_str() {
+            return broken("previous hash does not match the preceding entry");
+        }
+        if entry["seq"] != seq + 1 {
+            return broken("sequence number is out of order");
+        }
+        if entry_hash(&entry) != hash {
+            return broken("entry contents do not match its hash");
+        }
+
+        seq += 1;
+        last_hash = hash;
+    }
+    Ok(Ok((seq, last_hash)))
+}
+
+/// SHA-256 over the entry without its `hash` field. serde_json keeps object
+/// keys sorted, so the serialization is stable between writing and verifying.
+fn entry_hash(entry: &Value) -> String {
+    Sha256::digest(entry.to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use clap::ValueEnum;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::{Map, Value};
+
+use crate::ContactForm;
+
+/// Where a submission's fields are stored.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageMode {
+    /// One column per field, which the admin filters and reports query.
+    Columns,
+    /// One encrypted JSON `payload` column; only `id`, `created_at` and the
+    /// admin workflow columns stay in plaintext.
+    Blob,
+}
+
+/// Fields with their own key in the blob; everything else the form carries
+/// goes under `extra`.
+const TOP_LEVEL: [&str; 4] = ["name", "email", "subject", "message"];
+
+/// AES-256-GCM for `--storage-mode=blob`. A blob is a random nonce followed
+/// by the ciphertext and tag of
+/// `{"name", "email", "subject", "message", "extra": {...}}`.
+pub struct BlobCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl BlobCipher {
+    /// Reads `--storage-key-file`: 64 hex characters (32 bytes), e.g. from
+    /// `openssl rand -hex 32`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let hex = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let key = decode_hex(hex.trim())
+            .filter(|key| key.len() == 32)
+            .ok_or_else(|| format!("{}: expected 64 hex characters", path.display()))?;
+        Self::new(&key)
+    }
+
+    pub fn new(key: &[u8]) -> Result<Self, String> {
+        let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| "invalid key".to_string())?;
+        Ok(BlobCipher {
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    pub fn seal(&self, form: &ContactForm) -> Result<Vec<u8>, String> {
+        let mut fields = match serde_json::to_value(form) {
+            Ok(Value::Object(fields)) => fields,
+            _ => Map::new(),
+        };
+        let mut payload = Map::new();
+        for name in TOP_LEVEL {
+            payload.insert(name.to_string(), fields.remove(name).unwrap_or_default());
+        }
+        payload.insert("extra".to_string(), fields.into());
+
+        self.seal_bytes(Value::Object(payload).to_string().into_bytes())
+    }
+
+    /// Seals free text, e.g. an admin's reply to the submitter.
+    pub fn seal_text(&self, text: &str) -> Result<Vec<u8>, String> {
+        self.seal_bytes(text.as_bytes().to_vec())
+    }
+
+    fn seal_bytes(&self, mut sealed: Vec<u8>) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| "no randomness for the nonce".to_string())?;
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| "encryption failed".to_string())?;
+
+        let mut blob = nonce.to_vec();
+        blob.append(&mut sealed);
+        Ok(blob)
+    }
+
+    pub fn open(&self, blob: &[u8]) -> Result<ContactForm, String> {
+        let plain = self.open_bytes(blob)?;
+        let mut payload: Map<String, Value> =
+            serde_json::from_slice(&plain).map_err(|e| e.to_string())?;
+        if let Some(Value::Object(extra)) = payload.remove("extra") {
+            payload.extend(extra);
+        }
+        serde_json::from_value(Value::Object(payload)).map_err(|e| e.to_string())
+    }
+
+    fn open_bytes(&self, blob: &[u8]) -> Result<Vec<u8>, String> {
+        if blob.len() < NONCE_LEN {
+            return Err("payload is truncated".to_string());
+        }
+        let (nonce, sealed) = blob.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "bad nonce".to_string())?;
+        let mut sealed = sealed.to_vec();
+        let plain = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| "payload does not decrypt with this key".to_string())?;
+        Ok(plain.to_vec())
+    }
+}
+
+/// A column `--encrypt-fields` can encrypt.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SealedField {
+    Name,
+    Email,
+    Subject,
+    Message,
+}
+
+/// Marks an encrypted column value; unmarked values, e.g. from before the
+/// field was encrypted, are read as they are.
+const SEALED_PREFIX: &str = "enc:";
+
+/// `--encrypt-fields`: encrypts the chosen columns in place, as `enc:`
+/// followed by the base64 nonce and ciphertext, with the `--storage-key-file`
+/// key. The other columns stay searchable. Empty values are left empty.
+pub struct FieldCipher {
+    cipher: BlobCipher,
+    fields: Vec<SealedField>,
+}
+
+impl FieldCipher {
+    pub fn new(cipher: BlobCipher, fields: Vec<SealedField>) -> Self {
+        FieldCipher { cipher, fields }
+    }
+
+    pub fn seals(&self, field: SealedField) -> bool {
+        self.fields.contains(&field)
+    }
+
+    /// A copy of `form` with the chosen fields encrypted, for storing.
+    pub fn seal(&self, form: &ContactForm) -> Result<ContactForm, String> {
+        let mut sealed = form.clone();
+        for (field, value) in [
+            (SealedField::Name, &mut sealed.name),
+            (SealedField::Email, &mut sealed.email),
+            (SealedField::Subject, &mut sealed.subject),
+            (SealedField::Message, &mut sealed.message),
+        ] {
+            if self.seals(field) && !value.is_empty() {
+                let blob = self.cipher.seal_text(value)?;
+                *value = format!("{}{}", SEALED_PREFIX, STANDARD.encode(blob));
+            }
+        }
+        Ok(sealed)
+    }
+
+    /// The plaintext of a stored `field` value. Values of fields no longer
+    /// encrypted are still opened, so dropping a field from
+    /// `--encrypt-fields` does not hide what was stored before.
+    pub fn open(&self, field: SealedField, stored: String) -> Result<String, String> {
+        let Some(encoded) = stored.strip_prefix(SEALED_PREFIX) else {
+            return Ok(stored);
+        };
+        let opened = STANDARD
+            .decode(encoded)
+            .map_err(|e| e.to_string())
+            .and_then(|blob| self.cipher.open_bytes(&blob))
+            .and_then(|plain| String::from_utf8(plain).map_err(|e| e.to_string()));
+        match opened {
+            Ok(plain) => Ok(plain),
+            // Plaintext that happens to start with the marker.
+            Err(_) if !self.seals(field) => Ok(stored),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,445 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use time::{Date, Month, OffsetDateTime, UtcOffset};
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// `--business-hours`: when submissions are accepted, as a time zone then
+/// `;`-separated weekdays and local opening times, e.g.
+/// `Europe/Berlin;mon-fri 09:00-12:30,13:30-17:00;sat 10:00-14:00`.
+///
+/// Days are `mon` to `sun`, alone, as a range such as `mon-fri`, or several
+/// separated by commas; days not listed are closed. A range cannot run past
+/// midnight: `24:00` is the latest end, and a night shift is written as two
+/// ranges on consecutive days. The zone is `UTC` or an
+/// IANA name read from the system time zone database (`$TZDIR`, by default
+/// `/usr/share/zoneinfo`), so daylight saving time is followed, also past the
+/// transitions the database lists.
+#[derive(Clone, Debug)]
+pub struct BusinessHours {
+    zone: Zone,
+    /// Opening minutes of the day, Monday first.
+    week: [Vec<(u16, u16)>; 7],
+}
+
+impl BusinessHours {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let zone = spec.split(';').next().unwrap_or_default().trim();
+        Self::with_zone(spec, Zone::load(zone)?)
+    }
+
+    /// [`BusinessHours::parse`] with the zone read from TZif `data` instead
+    /// of the database, whatever `spec` names.
+    #[cfg(test)]
+    pub fn parse_with_tzif(spec: &str, data: &[u8]) -> Result<Self, String> {
+        Self::with_zone(spec, Zone::parse(data).ok_or("not a TZif file")?)
+    }
+
+    fn with_zone(spec: &str, zone: Zone) -> Result<Self, String> {
+        let mut week: [Vec<(u16, u16)>; 7] = Default::default();
+        let sections = spec.split(';').skip(1).map(str::trim);
+        for section in sections.filter(|section| !section.is_empty()) {
+            let (days, ranges) = section
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("{:?}: expected `DAYS HH:MM-HH:MM`", section))?;
+            let ranges = ranges
+                .split(',')
+                .map(|range| parse_range(range.trim()))
+                .collect::<Result<Vec<_>, _>>()?;
+            for day in parse_days(days)? {
+                week[day].extend(&ranges);
+            }
+        }
+        if week.iter().all(Vec::is_empty) {
+            return Err(format!(
+                "{:?} has no opening times, e.g. `UTC;mon-fri 09:00-17:00`",
+                spec
+            ));
+        }
+        Ok(BusinessHours { zone, week })
+    }
+
+    pub fn is_open(&self, now: OffsetDateTime) -> bool {
+        let local = self.zone.local(now);
+        let minute = minute_of_day(local);
+        self.week[local.weekday().number_days_from_monday() as usize]
+            .iter()
+            .any(|(start, end)| (*start..*end).contains(&minute))
+    }
+
+    /// Time until the next opening, for `Retry-After`.
+    pub fn opens_in(&self, now: OffsetDateTime) -> Duration {
+        let local = self.zone.local(now);
+        let minute = minute_of_day(local);
+        let next = (0..=7)
+            .flat_map(|days_ahead: u16| {
+                let day = local.weekday().nth_next(days_ahead as u8);
+                self.week[day.number_days_from_monday() as usize]
+                    .iter()
+                    .map(move |(start, _)| days_ahead * MINUTES_PER_DAY + start)
+            })
+            .filter(|start| *start > minute)
+            .min()
+            .unwrap_or(minute);
+        // Minutes counted in today's offset; a change of offset in between
+        // only makes clients retry an hour early or late.
+        Duration::from_secs(u64::from(next - minute) * 60)
+            .saturating_sub(Duration::from_secs(u64::from(local.second())))
+    }
+}
+
+fn minute_of_day(local: OffsetDateTime) -> u16 {
+    u16::from(local.hour()) * 60 + u16::from(local.minute())
+}
+
+fn parse_days(days: &str) -> Result<Vec<usize>, String> {
+    let index = |name: &str| {
+        DAYS.iter()
+            .position(|day| day.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| format!("unknown day {:?}; expected mon to sun", name))
+    };
+    let mut selected = Vec::new();
+    for group in days.split(',') {
+        match group.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (index(first)?, index(last)?);
+                if last < first {
+                    return Err(format!("{:?}: days run from mon to sun", group));
+                }
+                selected.extend(first..=last);
+            }
+            None => selected.push(index(group)?),
+        }
+    }
+    Ok(selected)
+}
+
+fn parse_range(range: &str) -> Result<(u16, u16), String> {
+    let parse_time = |time: &str| -> Option<u16> {
+        let (hours, minutes) = time.trim().split_once(':')?;
+        let (hours, minutes): (u16, u16) = (hours.parse().ok()?, minutes.parse().ok()?);
+        (minutes < 60 && hours * 60 + minutes <= MINUTES_PER_DAY).then_some(hours * 60 + minutes)
+    };
+    let (start, end) = range
+        .split_once('-')
+        .and_then(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+        .ok_or_else(|| format!("{:?}: expected HH:MM-HH:MM", range))?;
+    if end <= start {
+        return Err(format!(
+            "{:?}: a range must end after it starts; split ranges past midnight across two days",
+            range
+        ));
+    }
+    Ok((start, end))
+}
+
+/// UTC offsets from a TZif file, as found in the system time zone database.
+#[derive(Clone, Debug, Default)]
+struct Zone {
+    /// Unix times at which `offsets` of the same index take effect.
+    transitions: Vec<i64>,
+    offsets: Vec<i32>,
+    /// The offset before the first transition.
+    initial: i32,
+    /// The footer, for times after the last transition. Files built with
+    /// `zic -b slim`, the default since 2020, list no transitions it covers.
+    rule: Option<Rule>,
+}
+
+impl Zone {
+    fn load(name: &str) -> Result<Self, String> {
+        if name.eq_ignore_ascii_case("utc") {
+            return Ok(Zone::default());
+        }
+        if name.is_empty() || name.starts_with('/') || name.split('/').any(|part| part == "..") {
+            return Err(format!("{:?} is not a time zone name", name));
+        }
+        let path = env::var_os("TZDIR")
+            .map_or_else(|| PathBuf::from("/usr/share/zoneinfo"), PathBuf::from)
+            .join(name);
+        let data = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&data).ok_or_else(|| format!("{}: not a TZif file", path.display()))
+    }
+
+    /// Reads the 64-bit data and footer of version 2+ files, or the 32-bit
+    /// data of version 1 ones (RFC 8536). A footer that does not parse is
+    /// left out, so the last offset carries on.
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+        let version = *data.get(4)?;
+        let v1 = Header::read(data)?;
+        let (header, body, width) = if version >= b'2' {
+            let second = data.get(44 + v1.len(4)..)?;
+            (Header::read(second)?, second.get(44..)?, 8)
+        } else {
+            (v1, data.get(44..)?, 4)
+        };
+        let rule = (width == 8)
+            .then(|| body.get(header.len(8)..))
+            .flatten()
+            .and_then(|footer| footer.strip_prefix(b"\n"))
+            .and_then(|footer| footer.split(|b| *b == b'\n').next())
+            .and_then(|footer| std::str::from_utf8(footer).ok())
+            .and_then(Rule::parse);
+
+        let times = body.get(..header.timecnt * width)?;
+        let indices = body.get(header.timecnt * width..header.timecnt * (width + 1))?;
+        let types = body.get(header.timecnt * (width + 1)..)?;
+        let offset = |index: u8| -> Option<i32> {
+            let start = usize::from(index) * 6;
+            Some(i32::from_be_bytes(
+                types.get(start..start + 4)?.try_into().ok()?,
+            ))
+        };
+        let transitions = times
+            .chunks(width)
+            .map(|time| match width {
+                8 => i64::from_be_bytes(time.try_into().unwrap()),
+                _ => i64::from(i32::from_be_bytes(time.try_into().unwrap())),
+            })
+            .collect();
+        let offsets = indices
+            .iter()
+            .map(|index| offset(*index))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Zone {
+            transitions,
+            offsets,
+            initial: offset(0).filter(|_| header.typecnt > 0)?,
+            rule,
+        })
+    }
+
+    fn local(&self, now: OffsetDateTime) -> OffsetDateTime {
+        let applied = self
+            .transitions
+            .partition_point(|transition| *transition <= now.unix_timestamp());
+        let offset = match (applied, &self.rule) {
+            (n, Some(rule)) if n == self.transitions.len() => rule.offset(now),
+            (0, _) => self.initial,
+            (n, _) => self.offsets[n - 1],
+        };
+        let offset = UtcOffset::from_whole_seconds(offset).unwrap_or(UtcOffset::UTC);
+        now.to_offset(offset)
+    }
+}
+
+/// A POSIX TZ string as found in a TZif footer, such as
+/// `CET-1CEST,M3.5.0,M10.5.0/3`: the standard offset and, for zones with
+/// daylight saving time, its offset and yearly start and end.
+#[derive(Clone, Debug)]
+struct Rule {
+    standard: i32,
+    daylight: Option<(i32, Change, Change)>,
+}
+
+/// The local day and time, in the offset in effect before it, of a change
+/// between standard and daylight saving time.
+#[derive(Clone, Copy, Debug)]
+struct Change {
+    day: RuleDay,
+    seconds: i32,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum RuleDay {
+    /// `Jn`: 1 to 365, never counting February 29.
+    Julian(u16),
+    /// `n`: 0 to 365, counting February 29.
+    Ordinal(u16),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` (5 is the last) of
+    /// month `m`.
+    Weekday { month: u8, week: u8, weekday: u8 },
+}
+
+impl Rule {
+    fn parse(spec: &str) -> Option<Self> {
+        let mut rest = spec;
+        skip_name(&mut rest)?;
+        // POSIX offsets count hours west of UTC.
+        let standard = -take_offset(&mut rest)?;
+        if rest.is_empty() {
+            return Some(Rule {
+                standard,
+                daylight: None,
+            });
+        }
+        skip_name(&mut rest)?;
+        let daylight = match rest.starts_with(',') {
+            true => standard + 3600,
+            false => -take_offset(&mut rest)?,
+        };
+        let (start, end) = rest.strip_prefix(',')?.split_once(',')?;
+        Some(Rule {
+            standard,
+            daylight: Some((daylight, Change::parse(start)?, Change::parse(end)?)),
+        })
+    }
+
+    fn offset(&self, now: OffsetDateTime) -> i32 {
+        let Some((daylight, start, end)) = self.daylight else {
+            return self.standard;
+        };
+        let standard = UtcOffset::from_whole_seconds(self.standard).unwrap_or(UtcOffset::UTC);
+        let year = now.to_offset(standard).year();
+        let (Some(start), Some(end)) = (start.at(year, self.standard), end.at(year, daylight))
+        else {
+            return self.standard;
+        };
+        let now = now.unix_timestamp();
+        // South of the equator daylight saving time spans the new year.
+        let in_daylight = match start < end {
+            true => (start..end).contains(&now),
+            false => !(end..start).contains(&now),
+        };
+        match in_daylight {
+            true => daylight,
+            false => self.standard,
+        }
+    }
+}
+
+impl Change {
+    /// `DAY[/TIME]`; the time defaults to 02:00 and may be negative or past
+    /// 24:00.
+    fn parse(spec: &str) -> Option<Self> {
+        let (day, seconds) = match spec.split_once('/') {
+            Some((day, time)) => (day, parse_seconds(time)?),
+            None => (spec, 2 * 3600),
+        };
+        let day = if let Some(weekday) = day.strip_prefix('M') {
+            let mut parts = weekday.split('.').map(|part| part.parse::<u8>().ok());
+            let (month, week, weekday) = (parts.next()??, parts.next()??, parts.next()??);
+            if parts.next().is_some()
+                || !(1..=12).contains(&month)
+                || !(1..=5).contains(&week)
+                || weekday > 6
+            {
+                return None;
+            }
+            RuleDay::Weekday {
+                month,
+                week,
+                weekday,
+            }
+        } else if let Some(day) = day.strip_prefix('J') {
+            RuleDay::Julian(day.parse().ok().filter(|day| (1..=365).contains(day))?)
+        } else {
+            RuleDay::Ordinal(day.parse().ok().filter(|day| *day <= 365)?)
+        };
+        Some(Change { day, seconds })
+    }
+
+    /// Unix time of the change in `year`, in a place at `offset`.
+    fn at(self, year: i32, offset: i32) -> Option<i64> {
+        let date = match self.day {
+            RuleDay::Julian(day) => {
+                let leap = time::util::is_leap_year(year) && day >= 60;
+                Date::from_ordinal_date(year, day + u16::from(leap)).ok()?
+            }
+            RuleDay::Ordinal(day) => Date::from_ordinal_date(year, day + 1).ok()?,
+            RuleDay::Weekday {
+                month,
+                week,
+                weekday,
+            } => {
+                let month = Month::try_from(month).ok()?;
+                let first = Date::from_calendar_date(year, month, 1).ok()?;
+                let first_match = 1 + (weekday + 7 - first.weekday().number_days_from_sunday()) % 7;
+                let mut day = first_match + (week - 1) * 7;
+                while day > month.length(year) {
+                    day -= 7;
+                }
+                Date::from_calendar_date(year, month, day).ok()?
+            }
+        };
+        Some(
+            date.midnight().assume_utc().unix_timestamp() + i64::from(self.seconds)
+                - i64::from(offset),
+        )
+    }
+}
+
+/// Skips a zone abbreviation: three or more letters, or any text in `<>`.
+fn skip_name(rest: &mut &str) -> Option<()> {
+    let len = match rest.strip_prefix('<') {
+        Some(quoted) => quoted.find('>')? + 2,
+        None => rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len()),
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+/// Takes a leading `[+-]hh[:mm[:ss]]` off `rest`.
+fn take_offset(rest: &mut &str) -> Option<i32> {
+    let len = rest
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, ':' | '+' | '-')))
+        .unwrap_or(rest.len());
+    let (offset, tail) = rest.split_at(len);
+    *rest = tail;
+    parse_seconds(offset)
+}
+
+/// `[+-]hh[:mm[:ss]]` as seconds.
+fn parse_seconds(time: &str) -> Option<i32> {
+    let (sign, time) = match time.strip_prefix('-') {
+        Some(time) => (-1, time),
+        None => (1, time.strip_prefix('+').unwrap_or(time)),
+    };
+    let parts: Vec<&str> = time.split(':').collect();
+    if parts.len() > 3 || parts.iter().any(|part| part.is_empty() || part.len() > 3) {
+        return None;
+    }
+    let mut seconds = 0;
+    for (part, unit) in parts.iter().zip([3600, 60, 1]) {
+        seconds += part.parse::<i32>().ok()? * unit;
+    }
+    Some(sign * seconds)
+}
+
+struct Header {
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn read(data: &[u8]) -> Option<Self> {
+        let count = |index: usize| -> Option<usize> {
+            let start = 20 + index * 4;
+            Some(u32::from_be_bytes(data.get(start..start + 4)?.try_into().ok()?) as usize)
+        };
+        Some(Header {
+            isutcnt: count(0)?,
+            isstdcnt: count(1)?,
+            leapcnt: count(2)?,
+            timecnt: count(3)?,
+            typecnt: count(4)?,
+            charcnt: count(5)?,
+        })
+    }
+
+    /// Size of the data block after this header, with `width`-byte times.
+    fn len(&self, width: usize) -> usize {
+        self.timecnt * (width + 1)
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (width + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+
+/// How often the database size is measured.
+const INTERVAL: Duration = Duration::from_secs(60);
+/// Oldest rows removed per step while pruning towards the size cap.
+const PRUNE_BATCH: i64 = 100;
+
+/// What happens once the database is over `--max-db-size-mb` or `--max-rows`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum DbFullPolicy {
+    /// Refuse new submissions with 503 until space is freed.
+    Reject,
+    /// Delete the oldest submissions until back under the cap.
+    Prune,
+}
+
+pub struct Limits {
+    pub max_bytes: Option<u64>,
+    pub max_rows: Option<u64>,
+    pub policy: DbFullPolicy,
+}
+
+/// Latest measurement of the database, shared by all workers.
+#[derive(Default)]
+pub struct DbCapacity {
+    size_bytes: AtomicU64,
+    rows: AtomicU64,
+    full: AtomicBool,
+}
+
+impl DbCapacity {
+    /// Whether submissions should be refused under the `reject` policy.
+    pub fn is_full(&self) -> bool {
+        self.full.load(Ordering::Acquire)
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "size_bytes": self.size_bytes.load(Ordering::Relaxed),
+            "rows": self.rows.load(Ordering::Relaxed),
+            "full": self.is_full(),
+        })
+    }
+
+    /// Measures now and then every minute on a background thread with its
+    /// own connection, applying `limits` each time.
+    pub fn spawn_monitor(self: Arc<Self>, conn: Connection, limits: Limits) {
+        thread::spawn(move || loop {
+            if let Err(e) = self.check(&conn, &limits) {
+                eprintln!("Database size check error: {}", e);
+            }
+            thread::sleep(INTERVAL);
+        });
+    }
+
+    fn check(&self, conn: &Connection, limits: &Limits) -> rusqlite::Result<()> {
+        let (mut bytes, mut rows) = measure(conn)?;
+        let over = |bytes: u64, rows: u64| {
+            limits.max_bytes.is_some_and(|max| bytes > max)
+                || limits.max_rows.is_some_and(|max| rows > max)
+        };
+
+        if over(bytes, rows) {
+            if let DbFullPolicy::Prune = limits.policy {
+                let mut pruned = 0;
+                while over(bytes, rows) && rows > 0 {
+                    let excess_rows = limits
+                        .max_rows
+                        .map_or(0, |max| rows.saturating_sub(max) as i64);
+                    let batch = excess_rows.max(PRUNE_BATCH);
+                    conn.execute(
+                        "DELETE FROM replies WHERE contact_id IN
+                         (SELECT id FROM contacts ORDER BY created_at, id LIMIT ?1)",
+                        params![batch],
+                    )?;
+                    pruned += conn.execute(
+                        "DELETE FROM contacts WHERE id IN
+                         (SELECT id FROM contacts ORDER BY created_at, id LIMIT ?1)",
+                        params![batch],
+                    )?;
+                    (bytes, rows) = measure(conn)?;
+                }
+                eprintln!(
+                    "Database over its size cap: pruned {} oldest submissions",
+                    pruned
+                );
+            }
+        }
+
+        self.size_bytes.store(bytes, Ordering::Relaxed);
+        self.rows.store(rows, Ordering::Relaxed);
+
+        let full = matches!(limits.policy, DbFullPolicy::Reject) && over(bytes, rows);
+        if full != self.full.swap(full, Ordering::AcqRel) {
+            if full {
+                eprintln!(
+                    "Database over its size cap ({} bytes, {} rows): rejecting new submissions",
+                    bytes, rows
+                );
+            } else {
+                eprintln!("Database back under its size cap: accepting submissions");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bytes in use (excluding free pages, which deletes leave behind until a
+/// VACUUM) and the number of stored submissions.
+fn measure(conn: &Connection) -> rusqlite::Result<(u64, u64)> {
+    let bytes: i64 = conn.query_row(
+        "SELECT (page_count - freelist_count) * page_size
+         FROM pragma_page_count, pragma_freelist_count, pragma_page_size",
+        [],
+        |row| row.get(0),
+    )?;
+    let rows: i64 = conn.query_row("SELECT COUNT(*) FROM contacts", [], |row| row.get(0))?;
+    Ok((bytes as u64, rows as u64))
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+use awc::Client;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CaptchaProvider {
+    Recaptcha,
+    Hcaptcha,
+    Turnstile,
+}
+
+pub enum CaptchaError {
+    /// The provider answered and did not accept the token.
+    Rejected,
+    /// The provider could not be reached or returned something unexpected.
+    Unavailable(String),
+}
+
+impl fmt::Display for CaptchaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptchaError::Rejected => write!(f, "captcha token rejected"),
+            CaptchaError::Unavailable(reason) => {
+                write!(f, "captcha provider unavailable: {}", reason)
+            }
+        }
+    }
+}
+
+pub type VerifyFuture<'a> = Pin<Box<dyn Future<Output = Result<(), CaptchaError>> + 'a>>;
+
+pub trait CaptchaVerifier {
+    fn verify<'a>(&'a self, token: &'a str, remote_ip: Option<&'a str>) -> VerifyFuture<'a>;
+}
+
+/// Reads the provider's secret key from `path`, ignoring surrounding
+/// whitespace such as a trailing newline.
+pub fn load_secret(path: &Path) -> Result<String, String> {
+    let secret = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(format!("{}: no captcha secret", path.display()));
+    }
+    Ok(secret.to_string())
+}
+
+/// Builds the verifier for `provider`. `min_score` only applies to
+/// reCAPTCHA v3, whose responses carry a score; v2 responses are accepted
+/// on `success` alone.
+pub fn verifier(
+    provider: CaptchaProvider,
+    secret: String,
+    min_score: Option<f64>,
+) -> Box<dyn CaptchaVerifier> {
+    let client = Client::default();
+    match provider {
+        CaptchaProvider::Recaptcha => Box::new(Recaptcha {
+            client,
+            secret,
+            min_score,
+        }),
+        CaptchaProvider::Hcaptcha => Box::new(Hcaptcha { client, secret }),
+        CaptchaProvider::Turnstile => Box::new(Turnstile { client, secret }),
+    }
+}
+
+struct Recaptcha {
+    client: Client,
+    secret: String,
+    min_score: Option<f64>,
+}
+
+struct Hcaptcha {
+    client: Client,
+    secret: String,
+}
+
+struct Turnstile {
+    client: Client,
+    secret: String,
+}
+
+impl CaptchaVerifier for Recaptcha {
+    fn verify<'a>(&'a self, token: &'a str, remote_ip: Option<&'a str>) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            let response = siteverify(
+                &self.client,
+                "https://www.google.com/recaptcha/api/siteverify",
+                &self.secret,
+                token,
+                remote_ip,
+            )
+            .await?;
+
+            match (response.score, self.min_score) {
+                (Some(score), Some(min_score)) if score < min_score => Err(CaptchaError::Rejected),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+impl CaptchaVerifier for Hcaptcha {
+    fn verify<'a>(&'a self, token: &'a str, remote_ip: Option<&'a str>) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            siteverify(
+                &self.client,
+                "https://api.hcaptcha.com/siteverify",
+                &self.secret,
+                token,
+                remote_ip,
+            )
+            .await
+            .map(|_| ())
+        })
+    }
+}
+
+impl CaptchaVerifier for Turnstile {
+    fn verify<'a>(&'a self, token: &'a str, remote_ip: Option<&'a str>) -> VerifyFuture<'a> {
+        Box::pin(async move {
+            siteverify(
+                &self.client,
+                "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+                &self.secret,
+                token,
+                remote_ip,
+            )
+            .await
+            .map(|_| ())
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    score: Option<f64>,
+}
+
+/// All three providers share the reCAPTCHA siteverify protocol: a form
+/// POST of `secret`, `response` and optional `remoteip`, answered with JSON
+/// containing at least `success`.
+async fn siteverify(
+    client: &Client,
+    url: &str,
+    secret: &str,
+    token: &str,
+    remote_ip: Option<&str>,
+) -> Result<SiteVerifyResponse, CaptchaError> {
+    let mut form = vec![("secret", secret), ("response", token)];
+    if let Some(ip) = remote_ip {
+        form.push(("remoteip", ip));
+    }
+
+    let mut response = client
+        .post(url)
+        .send_form(&form)
+        .await
+        .map_err(|e| CaptchaError::Unavailable(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(CaptchaError::Unavailable(format!(
+            "siteverify returned {}",
+            response.status()
+        )));
+    }
+
+    let body: SiteVerifyResponse = response
+        .json()
+        .await
+        .map_err(|e| CaptchaError::Unavailable(e.to_string()))?;
+
+    if body.success {
+        Ok(body)
+    } else {
+        Err(CaptchaError::Rejected)
+    }
+}
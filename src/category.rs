@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::Path;
+
+use regex::{Regex, RegexSet};
+
+/// Stored when no rule matches.
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+/// `--category-rules`: assigns each submission a triage category from
+/// keywords in its subject and message.
+///
+/// The file has one rule per line, `category: keyword, keyword, ...`; blank
+/// lines and lines starting with `#` are ignored. Keywords match
+/// case-insensitively as whole words, and a keyword of several words matches
+/// them separated by any whitespace. When keywords of several categories
+/// occur, the rule listed first wins, so put the most specific categories
+/// (e.g. `spam`) at the top. A category may appear on several lines; its
+/// position is that of its first line.
+pub struct Categorizer {
+    categories: Vec<String>,
+    /// One pattern per category, in file order.
+    rules: RegexSet,
+}
+
+impl Categorizer {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let whitespace = Regex::new(r"\s+").unwrap();
+        let mut categories: Vec<String> = Vec::new();
+        let mut keywords: Vec<Vec<String>> = Vec::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (category, words) = line
+                .split_once(':')
+                .ok_or_else(|| format!("line {}: expected `category: keyword, ...`", number + 1))?;
+            let category = category.trim();
+            if category.is_empty() {
+                return Err(format!("line {}: missing category", number + 1));
+            }
+
+            let index = match categories.iter().position(|known| known == category) {
+                Some(index) => index,
+                None => {
+                    categories.push(category.to_string());
+                    keywords.push(Vec::new());
+                    categories.len() - 1
+                }
+            };
+            for word in words.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+                let escaped = whitespace
+                    .split(word)
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join(r"\s+");
+                let starts_word = word.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+                let ends_word = word.ends_with(|c: char| c.is_alphanumeric() || c == '_');
+                keywords[index].push(format!(
+                    "{}{}{}",
+                    if starts_word { r"\b" } else { "" },
+                    escaped,
+                    if ends_word { r"\b" } else { "" }
+                ));
+            }
+        }
+
+        if let Some(index) = keywords.iter().position(Vec::is_empty) {
+            return Err(format!("category {:?} has no keywords", categories[index]));
+        }
+        if categories.is_empty() {
+            return Err("no rules".to_string());
+        }
+        let rules = RegexSet::new(
+            keywords
+                .iter()
+                .map(|words| format!("(?i)(?:{})", words.join("|"))),
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(Categorizer { categories, rules })
+    }
+
+    /// The first category, in file order, whose keywords occur in the
+    /// subject or message.
+    pub fn categorize(&self, subject: &str, message: &str) -> &str {
+        let text = format!("{}\n{}", subject, message);
+        self.rules
+            .matches(&text)
+            .iter()
+            .next()
+            .map_or(UNCATEGORIZED, |index| &self.categories[index])
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+enum State {
+    Closed,
+    /// Attempts are skipped until the cooldown from `since` has passed.
+    Open {
+        since: Instant,
+    },
+    /// The cooldown passed and one attempt is in flight; the rest are still
+    /// skipped until it reports back.
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+struct Inner {
+    state: State,
+    consecutive_failures: u32,
+}
+
+/// Stops calling a notification channel that keeps failing, so a broken
+/// SMTP server or webhook does not add its timeout to every submission.
+///
+/// After `threshold` consecutive failures the circuit opens and attempts are
+/// skipped for `cooldown`. The first attempt after that decides: success
+/// closes the circuit, failure keeps it open for another cooldown. Only the
+/// transitions are logged, not each skipped attempt.
+pub struct CircuitBreaker {
+    name: &'static str,
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            name,
+            threshold,
+            cooldown,
+            inner: Mutex::new(Inner {
+                state: State::Closed,
+                consecutive_failures: 0,
+            }),
+        }
+    }
+
+    /// Whether to attempt a send now. Must be followed by [`record`] when it
+    /// returns true.
+    ///
+    /// [`record`]: CircuitBreaker::record
+    pub fn allows(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            State::Closed => true,
+            State::Open { since } | State::HalfOpen { since } => {
+                if since.elapsed() < self.cooldown {
+                    return false;
+                }
+                inner.state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+                true
+            }
+        }
+    }
+
+    pub fn record(&self, succeeded: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if succeeded {
+            if !matches!(inner.state, State::Closed) {
+                eprintln!("{} recovered; sending notifications again", self.name);
+            }
+            inner.state = State::Closed;
+            inner.consecutive_failures = 0;
+            return;
+        }
+
+        inner.consecutive_failures += 1;
+        match inner.state {
+            State::Closed if inner.consecutive_failures < self.threshold => {}
+            State::Closed => eprintln!(
+                "{} failed {} times in a row; skipping it for {}s",
+                self.name,
+                inner.consecutive_failures,
+                self.cooldown.as_secs()
+            ),
+            State::Open { .. } | State::HalfOpen { .. } => eprintln!(
+                "{} still failing; skipping it for another {}s",
+                self.name,
+                self.cooldown.as_secs()
+            ),
+        }
+        if inner.consecutive_failures >= self.threshold {
+            inner.state = State::Open {
+                since: Instant::now(),
+            };
+        }
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        let inner = self.inner.lock().unwrap();
+        let (state, retry_in) = match inner.state {
+            State::Closed => ("closed", None),
+            State::Open { since } => (
+                "open",
+                Some(self.cooldown.saturating_sub(since.elapsed()).as_secs()),
+            ),
+            State::HalfOpen { .. } => ("half_open", None),
+        };
+        serde_json::json!({
+            "state": state,
+            "consecutive_failures": inner.consecutive_failures,
+            "retry_in_secs": retry_in,
+        })
+    }
+}
+
+/// One breaker per configured notification channel, shared by all workers
+/// and reported by `/health`.
+#[derive(Default)]
+pub struct Circuits {
+    pub webhook: Option<CircuitBreaker>,
+    pub email: Option<CircuitBreaker>,
+}
+
+impl Circuits {
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "webhook": self.webhook.as_ref().map(CircuitBreaker::summary),
+            "email": self.email.as_ref().map(CircuitBreaker::summary),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const COOLDOWN: Duration = Duration::from_millis(50);
+
+    fn state(breaker: &CircuitBreaker) -> serde_json::Value {
+        breaker.summary()["state"].clone()
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new("Webhook", 3, COOLDOWN);
+        for _ in 0..2 {
+            assert!(breaker.allows());
+            breaker.record(false);
+        }
+        breaker.record(true);
+        for _ in 0..2 {
+            breaker.record(false);
+        }
+        assert!(breaker.allows());
+        assert_eq!(state(&breaker), "closed");
+
+        breaker.record(false);
+        assert!(!breaker.allows());
+        assert_eq!(state(&breaker), "open");
+        assert_eq!(breaker.summary()["consecutive_failures"], 3);
+    }
+
+    #[test]
+    fn a_single_probe_after_the_cooldown_decides() {
+        let breaker = CircuitBreaker::new("Email", 1, COOLDOWN);
+        breaker.record(false);
+        assert!(!breaker.allows());
+
+        thread::sleep(COOLDOWN);
+        assert!(breaker.allows());
+        assert_eq!(state(&breaker), "half_open");
+        assert!(!breaker.allows());
+        breaker.record(false);
+        assert_eq!(state(&breaker), "open");
+        assert!(!breaker.allows());
+
+        thread::sleep(COOLDOWN);
+        assert!(breaker.allows());
+        breaker.record(true);
+        assert_eq!(state(&breaker), "closed");
+        assert_eq!(breaker.summary()["consecutive_failures"], 0);
+        assert!(breaker.allows());
+    }
+}
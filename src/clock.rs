@@ -0,0 +1,52 @@
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+/// Wall-clock time for the time-window logic (dedup, email cooldown and
+/// quota, spam fill time, SLA and retention), so tests can move it instead
+/// of sleeping. SQL compares against [`Clock::sql_now`] rather than
+/// `'now'`. Rate limiting keeps the governor's own clock.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> OffsetDateTime;
+
+    /// `now()` in SQLite's `CURRENT_TIMESTAMP` format, the one `created_at`
+    /// is stored in.
+    fn sql_now(&self) -> String {
+        self.now()
+            .format(format_description!(
+                "[year]-[month]-[day] [hour]:[minute]:[second]"
+            ))
+            .unwrap_or_default()
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// A clock that only moves when told to.
+#[cfg(test)]
+pub struct ManualClock(std::sync::Mutex<OffsetDateTime>);
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock(std::sync::Mutex::new(
+            time::macros::datetime!(2024-01-01 12:00 UTC),
+        ))
+    }
+
+    pub fn advance(&self, by: std::time::Duration) {
+        *self.0.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.0.lock().unwrap()
+    }
+}
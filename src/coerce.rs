@@ -0,0 +1,36 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// A typed form value that may have arrived as a JSON string, as sent by
+/// form encoders that stringify everything.
+///
+/// With `--coerce-strings` a string is converted by trimming it and parsing
+/// it with the type's standard `FromStr`: decimal integers for numbers
+/// (`"42"`), and exactly `"true"`/`"false"` for booleans. Nothing else is
+/// coerced; strings that do not parse, or any string without the flag, are
+/// rejected during validation.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Coerce<T> {
+    Typed(T),
+    Text(String),
+}
+
+impl<T: FromStr + Copy> Coerce<T> {
+    /// Converts a string form in place when `lenient` and it parses.
+    pub fn coerce(&mut self, lenient: bool) {
+        if let (true, Coerce::Text(text)) = (lenient, &*self) {
+            if let Ok(value) = text.trim().parse() {
+                *self = Coerce::Typed(value);
+            }
+        }
+    }
+
+    pub fn typed(&self) -> Option<T> {
+        match self {
+            Coerce::Typed(value) => Some(*value),
+            Coerce::Text(_) => None,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::validation_error::ValidationError;
+use crate::{check_max_len, ContactForm, FORM_FIELDS};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    #[serde(rename = "if")]
+    condition: RawCondition,
+    then: RawRequirement,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCondition {
+    field: String,
+    equals: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRequirement {
+    field: String,
+    #[serde(default)]
+    required: bool,
+    max_len: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    if_field: &'static str,
+    equals: String,
+    field: &'static str,
+    required: bool,
+    max_len: Option<usize>,
+}
+
+/// `--conditional-rules`: checks that apply only when another field has a
+/// given value, from a JSON array such as
+///
+/// ```json
+/// [{"if": {"field": "subject", "equals": "Job Application"},
+///   "then": {"field": "message", "required": true, "max_len": 2000}}]
+/// ```
+///
+/// A condition holds when the field, trimmed, is exactly `equals`. Rules run
+/// after every built-in check, in file order, and the first one that fails
+/// is the error reported. Rules only add requirements: when several hold for
+/// the same field, all of them must pass, so the strictest `max_len` wins,
+/// and none can relax `--required-fields` or the built-in limits.
+#[derive(Clone, Debug, Default)]
+pub struct ConditionalRules(Vec<Rule>);
+
+impl ConditionalRules {
+    /// Reads and checks the rules file, naming the first malformed rule.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(Path::new(path)).map_err(|e| format!("{}: {}", path, e))?;
+        let raw: Vec<RawRule> =
+            serde_json::from_str(&source).map_err(|e| format!("{}: {}", path, e))?;
+
+        raw.into_iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                let field = |name: &str| {
+                    FORM_FIELDS
+                        .iter()
+                        .map(|(field, _)| *field)
+                        .find(|field| *field == name)
+                        .ok_or_else(|| {
+                            format!("{}: rule {}: unknown field {:?}", path, index + 1, name)
+                        })
+                };
+                if !rule.then.required && rule.then.max_len.is_none() {
+                    return Err(format!(
+                        "{}: rule {}: `then` needs `required` or `max_len`",
+                        path,
+                        index + 1
+                    ));
+                }
+                Ok(Rule {
+                    if_field: field(&rule.condition.field)?,
+                    equals: rule.condition.equals.trim().to_string(),
+                    field: field(&rule.then.field)?,
+                    required: rule.then.required,
+                    max_len: rule.then.max_len,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(ConditionalRules)
+    }
+
+    pub fn check(&self, form: &ContactForm) -> Result<(), ValidationError> {
+        for rule in &self.0 {
+            if form.field(rule.if_field).map(str::trim) != Some(rule.equals.as_str()) {
+                continue;
+            }
+            let value = form.field(rule.field).unwrap_or_default();
+            if rule.required && value.trim().is_empty() {
+                return Err(ValidationError::Required { field: rule.field });
+            }
+            if let Some(limit) = rule.max_len {
+                check_max_len(rule.field, value, limit)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::Path;
+
+use clap::ValueEnum;
+use ring::hmac;
+
+use crate::blob::decode_hex;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmailHashMode {
+    /// Store the address only.
+    Plaintext,
+    /// Store the address and its `email_hash`.
+    Both,
+    /// Store only the `email_hash`; the address is used for this
+    /// submission's notifications, then discarded.
+    HashOnly,
+}
+
+/// `--email-hash-mode`: a keyed hash of each submitter's address, stored in
+/// `email_hash`, so unique submitters can be counted without keeping the
+/// address. Addresses are trimmed and lowercased first, so the same person
+/// always gets the same hash. The key keeps the hashes from being matched
+/// against a list of known addresses; with a new key they change.
+pub struct EmailHasher {
+    key: hmac::Key,
+    pub mode: EmailHashMode,
+}
+
+impl EmailHasher {
+    /// Reads `--email-hash-key-file`: 64 hex characters (32 bytes), e.g.
+    /// from `openssl rand -hex 32`.
+    pub fn load(path: &Path, mode: EmailHashMode) -> Result<Self, String> {
+        let hex = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let key = decode_hex(hex.trim())
+            .filter(|key| key.len() == 32)
+            .ok_or_else(|| format!("{}: expected 64 hex characters", path.display()))?;
+        Ok(EmailHasher {
+            key: hmac::Key::new(hmac::HMAC_SHA256, &key),
+            mode,
+        })
+    }
+
+    pub fn hash(&self, email: &str) -> String {
+        hmac::sign(&self.key, email.trim().to_lowercase().as_bytes())
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn withholds_email(&self) -> bool {
+        self.mode == EmailHashMode::HashOnly
+    }
+}
@@ -0,0 +1,34 @@
+use std::net::IpAddr;
+
+use ipnetwork::IpNetwork;
+
+/// `--block-ips` and `--allow-ips`, checked against the client IP before a
+/// submission is read. A blocked address is refused even if it is also
+/// allowed; a non-empty allow list refuses everything not on it, including
+/// clients whose address cannot be determined.
+#[derive(Clone, Default)]
+pub struct IpFilter {
+    pub allow: Vec<IpNetwork>,
+    pub block: Vec<IpNetwork>,
+}
+
+impl IpFilter {
+    pub fn permits(&self, ip: Option<IpAddr>) -> bool {
+        let Some(ip) = ip.map(|ip| ip.to_canonical()) else {
+            return self.allow.is_empty();
+        };
+        if self.block.iter().any(|network| network.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|network| network.contains(ip))
+    }
+}
+
+/// Parses `203.0.113.7` or `203.0.113.0/24`; a bare address is a single-host
+/// network.
+pub fn parse_network(value: &str) -> Result<IpNetwork, String> {
+    value
+        .trim()
+        .parse::<IpNetwork>()
+        .map_err(|e| format!("invalid IP or CIDR range {:?}: {}", value, e))
+}
@@ -0,0 +1,62 @@
+/// Shape limits checked on the raw body before it is deserialized, so a
+/// submission with thousands of keys or deeply nested arrays is refused
+/// without building any of it.
+#[derive(Clone, Copy, Debug)]
+pub struct JsonLimits {
+    /// Keys allowed in the top-level object (`--max-json-keys`).
+    pub max_keys: usize,
+    /// Objects and arrays allowed inside one another, counting the top-level
+    /// object as 1 (`--max-json-depth`).
+    pub max_depth: usize,
+}
+
+pub enum Exceeded {
+    Keys,
+    Depth,
+}
+
+impl JsonLimits {
+    /// Scans `body` once, tracking strings so brackets and colons inside
+    /// them are not counted. Malformed JSON that stays within the limits is
+    /// left for the deserializer to report.
+    pub fn check(&self, body: &[u8]) -> Result<(), Exceeded> {
+        let mut depth = 0usize;
+        let mut keys = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut top_level_object = false;
+
+        for &byte in body {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => {
+                    if depth == 0 {
+                        top_level_object = byte == b'{';
+                    }
+                    depth += 1;
+                    if depth > self.max_depth {
+                        return Err(Exceeded::Depth);
+                    }
+                }
+                b'}' | b']' => depth = depth.saturating_sub(1),
+                b':' if depth == 1 && top_level_object => {
+                    keys += 1;
+                    if keys > self.max_keys {
+                        return Err(Exceeded::Keys);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
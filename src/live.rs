@@ -0,0 +1,133 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::rt::signal::unix::{signal, SignalKind};
+use actix_web::web::Bytes;
+use tokio::sync::{broadcast, mpsc, watch};
+
+/// Comment line sent while no submission arrives, so proxies and browsers
+/// keep an idle stream open.
+const HEARTBEAT: Duration = Duration::from_secs(15);
+/// Events a slow stream may fall behind by before it skips ahead.
+const CAPACITY: usize = 256;
+
+/// Fans stored submissions out to `GET /contacts/stream` clients as
+/// Server-Sent Events. Publishing costs nothing while nobody is watching.
+pub struct LiveFeed {
+    events: broadcast::Sender<Bytes>,
+    /// Set on shutdown, so open streams end instead of holding up the
+    /// graceful shutdown until its timeout.
+    closing: watch::Sender<bool>,
+}
+
+impl Default for LiveFeed {
+    fn default() -> Self {
+        LiveFeed {
+            events: broadcast::channel(CAPACITY).0,
+            closing: watch::channel(false).0,
+        }
+    }
+}
+
+impl LiveFeed {
+    pub fn is_watched(&self) -> bool {
+        self.events.receiver_count() > 0
+    }
+
+    /// Ends every open stream, now and as soon as one is opened.
+    pub fn close(&self) {
+        self.closing.send_replace(true);
+    }
+
+    /// Closes the streams once the server receives a signal that stops it.
+    pub fn close_on_shutdown(self: Arc<Self>) {
+        actix_web::rt::spawn(async move {
+            let (Ok(mut terminate), Ok(mut interrupt), Ok(mut quit)) = (
+                signal(SignalKind::terminate()),
+                signal(SignalKind::interrupt()),
+                signal(SignalKind::quit()),
+            ) else {
+                return;
+            };
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = interrupt.recv() => {}
+                _ = quit.recv() => {}
+            }
+            self.close();
+        });
+    }
+
+    pub fn publish(&self, id: i64, submission: &serde_json::Value) {
+        // Fails only when the last client left since `is_watched`.
+        let _ = self.events.send(event(id, submission));
+    }
+
+    /// A stream that sends `backlog` first, then live events and heartbeats
+    /// until the client goes away. The forwarding task ends, dropping its
+    /// subscription, on the first write after the response is dropped.
+    pub fn subscribe(&self, backlog: Vec<Bytes>) -> EventStream {
+        let mut events = self.events.subscribe();
+        let mut closing = self.closing.subscribe();
+        let (sender, receiver) = mpsc::channel(CAPACITY);
+        actix_web::rt::spawn(async move {
+            if *closing.borrow_and_update() {
+                return;
+            }
+            for event in backlog {
+                if sender.send(event).await.is_err() {
+                    return;
+                }
+            }
+            let mut heartbeat = tokio::time::interval(HEARTBEAT);
+            heartbeat.tick().await;
+            loop {
+                let next = tokio::select! {
+                    received = events.recv() => match received {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            Bytes::from(format!(": skipped {} submissions\n\n", missed))
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    },
+                    _ = heartbeat.tick() => Bytes::from_static(b": heartbeat\n\n"),
+                    _ = closing.changed() => return,
+                };
+                if sender.send(next).await.is_err() {
+                    return;
+                }
+                heartbeat.reset();
+            }
+        });
+        EventStream(receiver)
+    }
+}
+
+/// One `submission` event, with the row id as the event id.
+pub fn event(id: i64, submission: &serde_json::Value) -> Bytes {
+    Bytes::from(format!(
+        "event: submission\nid: {}\ndata: {}\n\n",
+        id, submission
+    ))
+}
+
+/// Response body of an open event stream.
+pub struct EventStream(mpsc::Receiver<Bytes>);
+
+impl MessageBody for EventStream {
+    type Error = std::convert::Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.0.poll_recv(cx).map(|event| event.map(Ok))
+    }
+}
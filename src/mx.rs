@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// How long a domain's answer is reused before it is looked up again.
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+/// Expired entries are swept once the cache grows past this many domains.
+const CACHE_SWEEP_LEN: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MxLookup {
+    Found,
+    /// The domain does not exist or publishes no MX records.
+    Missing,
+    /// The lookup timed out or the resolver failed; never cached.
+    Unavailable,
+}
+
+/// `--verify-mx`: checks that an email's domain can receive mail, caching
+/// answers per domain so repeat submitters only pay for one lookup.
+pub struct MxVerifier {
+    resolver: TokioAsyncResolver,
+    timeout: Duration,
+    cache: Mutex<HashMap<String, (bool, Instant)>>,
+}
+
+impl MxVerifier {
+    /// Uses the system resolver configuration, falling back to the resolver
+    /// crate's defaults when it cannot be read.
+    pub fn new(timeout: Duration) -> Self {
+        let mut opts = ResolverOpts::default();
+        opts.timeout = timeout;
+        opts.attempts = 1;
+        let resolver = match trust_dns_resolver::system_conf::read_system_conf() {
+            Ok((config, _)) => TokioAsyncResolver::tokio(config, opts),
+            Err(e) => {
+                eprintln!(
+                    "Could not read system DNS configuration, using defaults: {}",
+                    e
+                );
+                TokioAsyncResolver::tokio(ResolverConfig::default(), opts)
+            }
+        };
+        MxVerifier {
+            resolver,
+            timeout,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn lookup(&self, domain: &str) -> MxLookup {
+        let domain = domain.to_ascii_lowercase();
+        if let Some(&(found, at)) = self.cache.lock().unwrap().get(&domain) {
+            if at.elapsed() < CACHE_TTL {
+                return if found {
+                    MxLookup::Found
+                } else {
+                    MxLookup::Missing
+                };
+            }
+        }
+
+        // Trailing dot: treat the domain as fully qualified rather than
+        // appending the resolver's search domains.
+        let query = format!("{}.", domain.trim_end_matches('.'));
+        let found = match tokio::time::timeout(self.timeout, self.resolver.mx_lookup(query)).await {
+            Ok(Ok(records)) => records.iter().next().is_some(),
+            Ok(Err(e)) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => false,
+            Ok(Err(e)) => {
+                eprintln!("MX lookup for {} failed: {}", domain, e);
+                return MxLookup::Unavailable;
+            }
+            Err(_) => return MxLookup::Unavailable,
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= CACHE_SWEEP_LEN {
+            cache.retain(|_, (_, at)| at.elapsed() < CACHE_TTL);
+        }
+        cache.insert(domain, (found, Instant::now()));
+        if found {
+            MxLookup::Found
+        } else {
+            MxLookup::Missing
+        }
+    }
+}
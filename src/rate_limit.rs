@@ -0,0 +1,110 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use actix_governor::{KeyExtractor, SimpleKeyExtractionError};
+use actix_web::dev::{ConnectionInfo, ServiceRequest};
+use clap::ValueEnum;
+
+use crate::admin::constant_time_eq;
+
+/// Header carrying the caller's key for `--rate-limit-key api-key`.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// What the rate limiter counts requests against.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum RateLimitKey {
+    /// The client IP address.
+    Ip,
+    /// The client IP address together with the Origin header.
+    IpOrigin,
+    /// The `X-Api-Key` header when it is one of `--api-keys`, falling back
+    /// to the client IP otherwise.
+    ApiKey,
+}
+
+/// Keys governor buckets by [`RateLimitKey`], resolving the client IP with
+/// [`client_ip`].
+///
+/// Anything taken from request headers is chosen by the client: with
+/// `--trust-proxy` a client that can reach the server directly can spoof
+/// `X-Forwarded-For` to get a fresh bucket per request, and origins can be
+/// rotated freely unless something upstream checks them. API keys only get
+/// their own bucket when they are `api_keys`, so unknown ones cannot.
+#[derive(Clone)]
+pub struct ClientKeyExtractor {
+    pub strategy: RateLimitKey,
+    pub trust_proxy: bool,
+    /// `--api-keys`.
+    pub api_keys: Arc<[String]>,
+}
+
+impl KeyExtractor for ClientKeyExtractor {
+    type Key = String;
+    type KeyExtractionError = SimpleKeyExtractionError<&'static str>;
+
+    fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+        let ip = || {
+            client_ip(&req.connection_info(), self.trust_proxy)
+                .map(bucket_ip)
+                .ok_or_else(|| {
+                    SimpleKeyExtractionError::new(
+                        "Could not extract client IP address from request",
+                    )
+                })
+        };
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        match self.strategy {
+            RateLimitKey::Ip => Ok(format!("ip:{}", ip()?)),
+            RateLimitKey::IpOrigin => Ok(format!(
+                "ip:{} origin:{}",
+                ip()?,
+                header("origin").unwrap_or_default()
+            )),
+            RateLimitKey::ApiKey => {
+                let known = header(API_KEY_HEADER).and_then(|provided| {
+                    self.api_keys
+                        .iter()
+                        .position(|key| constant_time_eq(provided.as_bytes(), key.as_bytes()))
+                });
+                match known {
+                    Some(index) => Ok(format!("key:{}", index)),
+                    None => Ok(format!("ip:{}", ip()?)),
+                }
+            }
+        }
+    }
+}
+
+/// The client address: the TCP peer, or with `trust_proxy` the address
+/// reported by `Forwarded`/`X-Forwarded-For`.
+pub fn client_ip(info: &ConnectionInfo, trust_proxy: bool) -> Option<IpAddr> {
+    let addr = if trust_proxy {
+        info.realip_remote_addr()
+    } else {
+        info.peer_addr()
+    }?;
+
+    addr.parse::<IpAddr>()
+        .or_else(|_| addr.parse::<SocketAddr>().map(|socket| socket.ip()))
+        .ok()
+}
+
+/// IPv6 clients share a bucket per /56, which is what a single customer is
+/// usually assigned.
+fn bucket_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(ipv6) => {
+            let mut octets = ipv6.octets();
+            octets[7..].fill(0);
+            IpAddr::V6(octets.into())
+        }
+        ip => ip,
+    }
+}
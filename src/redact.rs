@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::ContactForm;
+
+/// Fields masked by `--redact-fields` wherever submissions leave the
+/// database other than through the full admin API: the submission and audit
+/// logs and `?redacted=true` admin reports.
+#[derive(Clone, Default)]
+pub struct Redaction {
+    fields: HashSet<String>,
+}
+
+impl Redaction {
+    pub fn new(fields: HashSet<String>) -> Self {
+        Redaction { fields }
+    }
+
+    /// `value` masked with [`redact`] if `field` is configured for redaction.
+    pub fn field(&self, field: &str, value: &str) -> String {
+        if self.fields.contains(field) {
+            redact(field, value)
+        } else {
+            value.to_string()
+        }
+    }
+
+    pub fn form(&self, form: &ContactForm) -> ContactForm {
+        ContactForm {
+            name: self.field("name", &form.name),
+            email: self.field("email", &form.email),
+            subject: self.field("subject", &form.subject),
+            message: self.field("message", &form.message),
+            locale: form
+                .locale
+                .as_deref()
+                .map(|locale| self.field("locale", locale)),
+            source_page: form
+                .source_page
+                .as_deref()
+                .map(|page| self.field("source_page", page)),
+            website: form
+                .website
+                .as_deref()
+                .map(|website| self.field("website", website)),
+            captcha_token: None,
+            attachment: None,
+            honeypot: None,
+            rendered_at: None,
+        }
+    }
+
+    /// A raw JSON request body with configured fields masked. Bodies that are
+    /// not a JSON object cannot be redacted and are withheld entirely when any
+    /// field is configured.
+    pub fn body(&self, raw: &[u8]) -> String {
+        if self.fields.is_empty() {
+            return String::from_utf8_lossy(raw).into_owned();
+        }
+        match serde_json::from_slice::<Value>(raw) {
+            Ok(Value::Object(mut object)) => {
+                for (field, value) in object.iter_mut() {
+                    if let Value::String(text) = value {
+                        if self.fields.contains(field) {
+                            *text = redact(field, text);
+                        }
+                    }
+                }
+                Value::Object(object).to_string()
+            }
+            _ => "<withheld: body is not a JSON object and cannot be redacted>".to_string(),
+        }
+    }
+}
+
+/// Masks all but the first character of `value`. Emails keep their domain,
+/// so `jane@example.com` becomes `j***@example.com`.
+pub fn redact(field: &str, value: &str) -> String {
+    let mask = |part: &str| match part.chars().next() {
+        Some(first) => format!("{}***", first),
+        None => String::new(),
+    };
+
+    match (field, value.rsplit_once('@')) {
+        ("email", Some((local, domain))) => format!("{}@{}", mask(local), domain),
+        _ => mask(value),
+    }
+}
@@ -0,0 +1,148 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use time::OffsetDateTime;
+
+/// Characters for `{random:N}`: digits and capitals without the easily
+/// confused 0/O, 1/I/L.
+const ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+
+#[derive(Clone, Debug)]
+enum Part {
+    Literal(String),
+    /// The row id, zero-padded to `width`.
+    Id {
+        width: usize,
+    },
+    Year,
+    Month,
+    Day,
+    Random {
+        len: usize,
+    },
+}
+
+/// `--reference-format`: the human-friendly reference returned for each
+/// accepted submission and stored in its `reference` column, e.g.
+/// `CF-{year}-{id:6}` for `CF-2024-000123`, or `CF-{random:8}` to avoid
+/// exposing how many submissions there have been.
+///
+/// Placeholders are `{id}` or `{id:WIDTH}`, `{year}`, `{month}`, `{day}`
+/// (UTC, zero-padded) and `{random:LEN}`; `{{` and `}}` are literal braces.
+/// A template must contain `{id}` or `{random:LEN}` so references can be
+/// unique; the column has a unique index on top.
+#[derive(Clone, Debug)]
+pub struct ReferenceFormat {
+    parts: Vec<Part>,
+    rng: SystemRandom,
+}
+
+impl ReferenceFormat {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| format!("unclosed placeholder in {:?}", template))?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(placeholder(&rest[..end])?);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err(format!("unmatched }} in {:?}", template)),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        if !parts
+            .iter()
+            .any(|part| matches!(part, Part::Id { .. } | Part::Random { .. }))
+        {
+            return Err(format!(
+                "{:?} needs {{id}} or {{random:LEN}} to make references unique",
+                template
+            ));
+        }
+        Ok(ReferenceFormat {
+            parts,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// Whether rendering again can give a different reference after a
+    /// collision.
+    pub fn is_random(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Random { .. }))
+    }
+
+    pub fn render(&self, id: i64, now: OffsetDateTime) -> Result<String, String> {
+        let mut reference = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => reference.push_str(text),
+                Part::Id { width } => reference.push_str(&format!("{:0width$}", id)),
+                Part::Year => reference.push_str(&format!("{:04}", now.year())),
+                Part::Month => reference.push_str(&format!("{:02}", u8::from(now.month()))),
+                Part::Day => reference.push_str(&format!("{:02}", now.day())),
+                Part::Random { len } => {
+                    let mut bytes = vec![0u8; *len];
+                    self.rng
+                        .fill(&mut bytes)
+                        .map_err(|_| "no randomness for the reference".to_string())?;
+                    reference.extend(
+                        bytes
+                            .iter()
+                            .map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char),
+                    );
+                }
+            }
+        }
+        Ok(reference)
+    }
+}
+
+fn placeholder(spec: &str) -> Result<Part, String> {
+    let (name, arg) = match spec.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (spec, None),
+    };
+    let number = |what: &str, min: usize, max: usize| -> Result<usize, String> {
+        arg.and_then(|arg| arg.parse().ok())
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| format!("{{{}}}: {} must be {} to {}", spec, what, min, max))
+    };
+    match (name, arg) {
+        ("id", None) => Ok(Part::Id { width: 0 }),
+        ("id", Some(_)) => Ok(Part::Id {
+            width: number("width", 1, 20)?,
+        }),
+        ("year", None) => Ok(Part::Year),
+        ("month", None) => Ok(Part::Month),
+        ("day", None) => Ok(Part::Day),
+        ("random", _) => Ok(Part::Random {
+            len: number("length", 4, 32)?,
+        }),
+        _ => Err(format!(
+            "unknown placeholder {{{}}}; expected {{id}}, {{id:WIDTH}}, {{year}}, {{month}}, \
+             {{day}} or {{random:LEN}}",
+            spec
+        )),
+    }
+}
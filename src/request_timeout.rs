@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+
+use crate::{request_id, respond};
+
+/// `--request-timeout-ms`, shared by every worker.
+pub struct RequestTimeout(pub Duration);
+
+/// Answers 503 once the wrapped route has run for `--request-timeout-ms`,
+/// dropping the rest of its work. Wrap routes, not scopes: the request is
+/// kept for the 503, and a scope still has to match it to a route.
+///
+/// The handler is only interrupted where it waits, e.g. on captcha, MX or
+/// validation hook lookups, a tarpit delay, or a webhook or SMTP server.
+/// Database work holds the worker until it is done (up to
+/// `--db-busy-timeout-ms` on a locked database) but counts towards the
+/// limit, so a request that was slow to store answers 503 at its next wait.
+pub async fn middleware<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let Some(limit) = req
+        .app_data::<web::Data<RequestTimeout>>()
+        .map(|timeout| timeout.0)
+    else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+    let http_req = req.request().clone();
+    match tokio::time::timeout(limit, next.call(req)).await {
+        Ok(response) => Ok(response?.map_into_left_body()),
+        Err(_) => {
+            eprintln!(
+                "[{}] {}",
+                request_id::get(&http_req),
+                serde_json::json!({
+                    "event": "request_timeout",
+                    "path": http_req.path(),
+                    "limit_ms": limit.as_millis() as u64,
+                })
+            );
+            let response = respond::error(
+                &http_req,
+                StatusCode::SERVICE_UNAVAILABLE,
+                serde_json::json!({
+                    "error": "The request took too long, try again later",
+                    "code": "request_timeout",
+                }),
+            );
+            Ok(ServiceResponse::new(http_req, response).map_into_right_body())
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Response helpers shared by every handler so that `--response-envelope`
+//! changes the shape of all JSON bodies consistently.
+//!
+//! Flat mode returns `data` as-is and error objects as `{"error": message, ...}`.
+//! Envelope mode wraps both as `{"data": ..., "error": ..., "meta": ...}`,
+//! where the error object carries its message under `message` instead.
+
+use std::borrow::Cow;
+
+use actix_web::http::header::{self, Accept};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use quick_xml::escape::escape;
+use serde::Serialize;
+use serde_json::Value;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::request_id;
+
+#[derive(Clone, Copy, Default)]
+pub struct ResponseFormat {
+    pub envelope: bool,
+    /// Add `error_category` to error bodies (`--error-categories`).
+    pub categories: bool,
+}
+
+/// Coarse cause of a non-2xx response, used to label counters, log events and
+/// (optionally) error bodies so attacks can be told apart from typos. Error
+/// responses carry theirs as an extension, since a 400 may be either.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCategory {
+    Validation,
+    Security,
+    RateLimit,
+    Server,
+}
+
+impl ErrorCategory {
+    /// The category of an error that does not name one, by its status.
+    pub fn of_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorCategory::Security,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCategory::RateLimit,
+            status if status.is_server_error() => ErrorCategory::Server,
+            _ => ErrorCategory::Validation,
+        }
+    }
+
+    /// The category set by [`categorized_error`], or else by the status for
+    /// responses built elsewhere, such as the rate limiter's 429s.
+    pub fn of<B>(response: &HttpResponse<B>) -> Self {
+        response
+            .extensions()
+            .get::<ErrorCategory>()
+            .copied()
+            .unwrap_or_else(|| ErrorCategory::of_status(response.status()))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCategory::Validation => "validation",
+            ErrorCategory::Security => "security",
+            ErrorCategory::RateLimit => "rate_limit",
+            ErrorCategory::Server => "server",
+        }
+    }
+}
+
+fn categories_enabled(req: &HttpRequest) -> bool {
+    req.app_data::<web::Data<ResponseFormat>>()
+        .is_some_and(|format| format.categories)
+}
+
+fn envelope_enabled(req: &HttpRequest) -> bool {
+    req.app_data::<web::Data<ResponseFormat>>()
+        .is_some_and(|format| format.envelope)
+}
+
+fn meta(req: &HttpRequest) -> Value {
+    serde_json::json!({
+        "timestamp": OffsetDateTime::now_utc().format(&Rfc3339).unwrap_or_default(),
+        "request_id": request_id::get(req),
+    })
+}
+
+pub fn json(req: &HttpRequest, status: StatusCode, data: impl Serialize) -> HttpResponse {
+    let mut response = HttpResponse::build(status);
+    response.json(body(req, data))
+}
+
+fn body(req: &HttpRequest, data: impl Serialize) -> Value {
+    if envelope_enabled(req) {
+        serde_json::json!({
+            "data": data,
+            "error": null,
+            "meta": meta(req),
+        })
+    } else {
+        serde_json::to_value(data).unwrap_or(Value::Null)
+    }
+}
+
+/// Like [`json`], but answers with XML when the `Accept` header ranks
+/// `application/xml` or `text/xml` above JSON. For read endpoints consumed by
+/// systems that cannot take JSON; see [`to_xml`] for the mapping.
+pub fn negotiated(req: &HttpRequest, status: StatusCode, data: impl Serialize) -> HttpResponse {
+    let wants_xml = req
+        .get_header::<Accept>()
+        .and_then(|accept| {
+            accept
+                .ranked()
+                .into_iter()
+                .find(|mime| matches!(mime.subtype().as_str(), "json" | "xml"))
+        })
+        .is_some_and(|mime| mime.subtype() == "xml");
+
+    let mut response = HttpResponse::build(status);
+    response.insert_header((header::VARY, "Accept"));
+    if !wants_xml {
+        return response.json(body(req, data));
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    to_xml(&mut xml, "response", &body(req, data));
+    response.content_type("application/xml").body(xml)
+}
+
+/// Objects become elements named by their keys, arrays repeat an `<item>`
+/// element per entry and null is an empty element. Text is escaped, so
+/// markup in submitted messages arrives as text; characters XML 1.0 does not
+/// allow at all become U+FFFD.
+fn to_xml(out: &mut String, name: &str, value: &Value) {
+    if value.is_null() {
+        out.push_str(&format!("<{}/>", name));
+        return;
+    }
+    out.push_str(&format!("<{}>", name));
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                to_xml(out, key, value);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                to_xml(out, "item", item);
+            }
+        }
+        Value::String(text) => out.push_str(&escape(xml_text(text).as_ref())),
+        scalar => out.push_str(&scalar.to_string()),
+    }
+    out.push_str(&format!("</{}>", name));
+}
+
+/// `text` with the control characters other than tab, newline and carriage
+/// return, and the noncharacters U+FFFE and U+FFFF, replaced.
+fn xml_text(text: &str) -> Cow<'_, str> {
+    let illegal = |c: char| {
+        (c < ' ' && !matches!(c, '\t' | '\n' | '\r')) || matches!(c, '\u{FFFE}' | '\u{FFFF}')
+    };
+    if !text.contains(illegal) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.chars()
+            .map(|c| if illegal(c) { '\u{FFFD}' } else { c })
+            .collect(),
+    )
+}
+
+/// `error` must serialize to an object with the human-readable message
+/// under `"error"`, plus any machine-readable fields alongside it. Flat
+/// errors also gain a `request_id` so users can quote it in reports.
+pub fn error(req: &HttpRequest, status: StatusCode, error: impl Serialize) -> HttpResponse {
+    categorized_error(req, status, ErrorCategory::of_status(status), error)
+}
+
+/// Like [`error`], for rejections whose status does not tell their category,
+/// such as a 400 for a failed captcha.
+pub fn categorized_error(
+    req: &HttpRequest,
+    status: StatusCode,
+    category: ErrorCategory,
+    error: impl Serialize,
+) -> HttpResponse {
+    let mut response = HttpResponse::build(status);
+    response.extensions_mut().insert(category);
+    let mut error = serde_json::to_value(error).unwrap_or(Value::Null);
+
+    if categories_enabled(req) {
+        if let Some(object) = error.as_object_mut() {
+            object.insert("error_category".to_string(), category.as_str().into());
+        }
+    }
+
+    if !envelope_enabled(req) {
+        if let Some(object) = error.as_object_mut() {
+            object.insert("request_id".to_string(), request_id::get(req).into());
+        }
+        return response.json(error);
+    }
+
+    if let Some(object) = error.as_object_mut() {
+        if let Some(message) = object.remove("error") {
+            object.insert("message".to_string(), message);
+        }
+    }
+    response.json(serde_json::json!({
+        "data": null,
+        "error": error,
+        "meta": meta(req),
+    }))
+}
+
+/// Errors that have always been plain text in flat mode.
+pub fn text_error(
+    req: &HttpRequest,
+    status: StatusCode,
+    category: ErrorCategory,
+    message: &str,
+) -> HttpResponse {
+    if envelope_enabled(req) {
+        categorized_error(
+            req,
+            status,
+            category,
+            serde_json::json!({ "error": message }),
+        )
+    } else {
+        let mut response = HttpResponse::build(status);
+        response.extensions_mut().insert(category);
+        response.body(message.to_string())
+    }
+}
@@ -0,0 +1,246 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rusqlite::{params, Connection};
+use time::macros::format_description;
+
+use crate::clock::Clock;
+
+/// How often the retention task looks for expired submissions.
+const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Deletes submissions older than `days`, first appending them to a dated
+/// `contacts-YYYY-MM-DD.jsonl` file in `archive_dir` when one is set. Rows
+/// are only deleted once the archive has been written and synced to disk.
+/// Rows stored with `--storage-mode=blob` are archived still encrypted, as a
+/// base64 `payload`. Lines keep the `uuid`, `email_hash`, attachment and
+/// `signature`, so archived rows can be imported again and verified.
+pub struct Retention {
+    pub days: u32,
+    pub archive_dir: Option<PathBuf>,
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Retention {
+    /// Runs the cleanup now and then hourly on a background thread with its
+    /// own connection.
+    pub fn spawn(self, conn: Connection) {
+        thread::spawn(move || {
+            let mut conn = conn;
+            loop {
+                match self.run_once(&mut conn) {
+                    Ok(0) => {}
+                    Ok(deleted) => println!(
+                        "{}",
+                        serde_json::json!({"event": "retention", "deleted": deleted})
+                    ),
+                    Err(e) => eprintln!("Retention error: {}", e),
+                }
+                thread::sleep(INTERVAL);
+            }
+        });
+    }
+
+    fn run_once(&self, conn: &mut Connection) -> Result<usize, String> {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let cutoff = format!("-{} days", self.days);
+
+        let mut rows = tx
+            .prepare(
+                "SELECT id, name, email, subject, message, locale, source_page, site,
+                        created_at, handled_at, payload, replied_at, reference, category, website,
+                        uuid, email_hash, signature, attachment, attachment_type
+                 FROM contacts WHERE created_at < datetime(?2, ?1)
+                 ORDER BY id",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map(params![cutoff, self.clock.sql_now()], |row| {
+                    let mut archived = serde_json::json!({
+                        "id": row.get::<_, i64>(0)?,
+                        "name": row.get::<_, String>(1)?,
+                        "email": row.get::<_, String>(2)?,
+                        "subject": row.get::<_, String>(3)?,
+                        "message": row.get::<_, String>(4)?,
+                        "locale": row.get::<_, Option<String>>(5)?,
+                        "source_page": row.get::<_, Option<String>>(6)?,
+                        "site": row.get::<_, Option<String>>(7)?,
+                        "created_at": row.get::<_, String>(8)?,
+                        "handled_at": row.get::<_, Option<String>>(9)?,
+                        "replied_at": row.get::<_, Option<String>>(11)?,
+                        "reference": row.get::<_, Option<String>>(12)?,
+                        "category": row.get::<_, Option<String>>(13)?,
+                        "website": row.get::<_, Option<String>>(14)?,
+                        "uuid": row.get::<_, Option<String>>(15)?,
+                        "email_hash": row.get::<_, Option<String>>(16)?,
+                        "signature": row.get::<_, Option<String>>(17)?,
+                    });
+                    if let Some(payload) = row.get::<_, Option<Vec<u8>>>(10)? {
+                        archived["payload"] = STANDARD.encode(payload).into();
+                    }
+                    if let Some(attachment) = row.get::<_, Option<Vec<u8>>>(18)? {
+                        archived["attachment"] = STANDARD.encode(attachment).into();
+                        archived["attachment_type"] = row.get::<_, Option<String>>(19)?.into();
+                    }
+                    Ok(archived)
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .map_err(|e| e.to_string())?;
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(dir) = &self.archive_dir {
+            for row in &mut rows {
+                row["replies"] = replies(&tx, row["id"].as_i64())
+                    .map_err(|e| e.to_string())?
+                    .into();
+            }
+            self.archive(dir, &rows).map_err(|e| {
+                format!(
+                    "archive to {} failed, nothing deleted: {}",
+                    dir.display(),
+                    e
+                )
+            })?;
+        }
+
+        for row in &rows {
+            tx.execute(
+                "DELETE FROM replies WHERE contact_id = ?1",
+                params![row["id"].as_i64()],
+            )
+            .and_then(|_| {
+                tx.execute(
+                    "DELETE FROM contacts WHERE id = ?1",
+                    params![row["id"].as_i64()],
+                )
+            })
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(rows.len())
+    }
+
+    fn archive(&self, dir: &Path, rows: &[serde_json::Value]) -> std::io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let date = self
+            .clock
+            .now()
+            .format(format_description!("[year]-[month]-[day]"))
+            .unwrap_or_default();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(format!("contacts-{}.jsonl", date)))?;
+
+        let mut lines = String::new();
+        for row in rows {
+            lines.push_str(&row.to_string());
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes())?;
+        file.sync_all()
+    }
+}
+
+/// The replies sent for submission `id`, for its archive line.
+fn replies(conn: &Connection, id: Option<i64>) -> rusqlite::Result<Vec<serde_json::Value>> {
+    conn.prepare(
+        "SELECT message, payload, message_id, sent_at FROM replies
+         WHERE contact_id = ?1 ORDER BY id",
+    )?
+    .query_map(params![id], |row| {
+        let mut reply = serde_json::json!({
+            "message": row.get::<_, String>(0)?,
+            "message_id": row.get::<_, String>(2)?,
+            "sent_at": row.get::<_, String>(3)?,
+        });
+        if let Some(payload) = row.get::<_, Option<Vec<u8>>>(1)? {
+            reply["payload"] = STANDARD.encode(payload).into();
+        }
+        Ok(reply)
+    })?
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn retention(archive_dir: PathBuf) -> (Retention, Connection) {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::init_db(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO contacts (id, name, email, subject, message, created_at, uuid, signature)
+             VALUES (1, 'Old', 'old@example.com', 'Hi', 'Long ago', '2023-11-01 09:00:00',
+                     'uuid-1', 'v2:sig'),
+                    (2, 'New', 'new@example.com', 'Hi', 'Lately', '2023-12-31 09:00:00',
+                     'uuid-2', 'v2:sig');
+             INSERT INTO replies (contact_id, message, message_id, sent_at)
+             VALUES (1, 'Thanks', '<1@example.com>', '2023-11-02 09:00:00'),
+                    (2, 'Thanks', '<2@example.com>', '2024-01-01 09:00:00');",
+        )
+        .unwrap();
+        let retention = Retention {
+            days: 30,
+            archive_dir: Some(archive_dir),
+            clock: Arc::new(ManualClock::new()),
+        };
+        (retention, conn)
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn expired_rows_are_archived_then_deleted_with_their_replies() {
+        let dir = std::env::temp_dir().join(format!("retention-{}", std::process::id()));
+        let (retention, mut conn) = retention(dir.clone());
+
+        assert_eq!(retention.run_once(&mut conn), Ok(1));
+        let remaining: String = conn
+            .query_row("SELECT name FROM contacts", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, "New");
+        let reply_to: i64 = conn
+            .query_row("SELECT contact_id FROM replies", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(reply_to, 2);
+
+        let archive = fs::read_to_string(dir.join("contacts-2024-01-01.jsonl")).unwrap();
+        let lines: Vec<serde_json::Value> = archive
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["name"], "Old");
+        assert_eq!(lines[0]["uuid"], "uuid-1");
+        assert_eq!(lines[0]["signature"], "v2:sig");
+        assert_eq!(lines[0]["replies"][0]["message_id"], "<1@example.com>");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn nothing_is_deleted_when_the_archive_cannot_be_written() {
+        let blocker = std::env::temp_dir().join(format!("retention-file-{}", std::process::id()));
+        fs::write(&blocker, "").unwrap();
+        let (retention, mut conn) = retention(blocker.join("archive"));
+
+        assert!(retention.run_once(&mut conn).is_err());
+        assert_eq!(count(&conn, "contacts"), 2);
+        assert_eq!(count(&conn, "replies"), 2);
+        fs::remove_file(&blocker).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::Path;
+
+use ring::hmac;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, Row};
+
+use crate::blob::decode_hex;
+
+/// The columns a signature covers, in order: everything fixed when the row
+/// is stored. Columns updated later, such as `handled_at`, are left out, so
+/// handling or replying to a submission keeps its signature valid.
+pub const SIGNED_COLUMNS: &str = "id, name, email, subject, message, locale, source_page, site, \
+     created_at, attachment, attachment_type, payload, reference, category, website, uuid, \
+     email_hash";
+const SIGNED_COUNT: usize = 17;
+/// Marks signatures over all [`SIGNED_COLUMNS`]. Unmarked ones were made
+/// before columns were appended and cover only the first [`LEGACY_COUNT`],
+/// so rows signed then stay valid.
+const VERSION: &str = "v2:";
+const LEGACY_COUNT: usize = 14;
+
+/// How a row compares with its `signature` column.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Integrity {
+    Ok,
+    /// Stored before `--signing-key-file` was set.
+    Unsigned,
+    /// Edited outside the server since it was signed, or signed with
+    /// another key.
+    Tampered,
+}
+
+impl Integrity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Integrity::Ok => "ok",
+            Integrity::Unsigned => "unsigned",
+            Integrity::Tampered => "tampered",
+        }
+    }
+}
+
+/// `--signing-key-file`: an HMAC-SHA256 of each stored submission, kept in
+/// its `signature` column, so rows edited directly in the database can be
+/// told apart. The signature covers the row id too, so contents cannot be
+/// swapped between rows either. Deleting a row is not detected. Changing the
+/// key invalidates every existing signature: those rows then read as
+/// tampered.
+pub struct RowSigner {
+    key: hmac::Key,
+}
+
+impl RowSigner {
+    /// Reads 64 hex characters (32 bytes), e.g. from `openssl rand -hex 32`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let hex = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let key = decode_hex(hex.trim())
+            .filter(|key| key.len() == 32)
+            .ok_or_else(|| format!("{}: expected 64 hex characters", path.display()))?;
+        Ok(Self::new(&key))
+    }
+
+    pub fn new(key: &[u8]) -> Self {
+        RowSigner {
+            key: hmac::Key::new(hmac::HMAC_SHA256, key),
+        }
+    }
+
+    /// Signs row `id` as it is now stored; call it in the transaction that
+    /// stores the row.
+    pub fn sign_stored(&self, conn: &Connection, id: i64) -> rusqlite::Result<()> {
+        let signature = conn.query_row(
+            &format!("SELECT {} FROM contacts WHERE id = ?1", SIGNED_COLUMNS),
+            params![id],
+            |row| self.sign(row, 0),
+        )?;
+        conn.execute(
+            "UPDATE contacts SET signature = ?1 WHERE id = ?2",
+            params![signature, id],
+        )?;
+        Ok(())
+    }
+
+    /// Compares the [`SIGNED_COLUMNS`] starting at column `start` with the
+    /// `signature` column right after them.
+    pub fn check(&self, row: &Row, start: usize) -> rusqlite::Result<Integrity> {
+        let Some(signature) = row.get::<_, Option<String>>(start + SIGNED_COUNT)? else {
+            return Ok(Integrity::Unsigned);
+        };
+        let (signature, count) = match signature.strip_prefix(VERSION) {
+            Some(signature) => (signature, SIGNED_COUNT),
+            None => (signature.as_str(), LEGACY_COUNT),
+        };
+        let content = self.content(row, start, count)?;
+        let valid = decode_hex(signature)
+            .is_some_and(|signature| hmac::verify(&self.key, &content, &signature).is_ok());
+        Ok(if valid {
+            Integrity::Ok
+        } else {
+            Integrity::Tampered
+        })
+    }
+
+    fn sign(&self, row: &Row, start: usize) -> rusqlite::Result<String> {
+        let signature = hmac::sign(&self.key, &self.content(row, start, SIGNED_COUNT)?);
+        Ok(signature
+            .as_ref()
+            .iter()
+            .fold(VERSION.to_string(), |hex, b| hex + &format!("{:02x}", b)))
+    }
+
+    /// Each value tagged with its type and, for text and blobs, prefixed
+    /// with its length, so no two rows encode the same.
+    fn content(&self, row: &Row, start: usize, count: usize) -> rusqlite::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        for index in start..start + count {
+            match row.get_ref(index)? {
+                ValueRef::Null => content.push(0),
+                ValueRef::Integer(n) => {
+                    content.push(1);
+                    content.extend(n.to_be_bytes());
+                }
+                ValueRef::Real(x) => {
+                    content.push(2);
+                    content.extend(x.to_bits().to_be_bytes());
+                }
+                ValueRef::Text(bytes) => {
+                    content.push(3);
+                    content.extend((bytes.len() as u64).to_be_bytes());
+                    content.extend(bytes);
+                }
+                ValueRef::Blob(bytes) => {
+                    content.push(4);
+                    content.extend((bytes.len() as u64).to_be_bytes());
+                    content.extend(bytes);
+                }
+            }
+        }
+        Ok(content)
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixStream;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// First delay before reconnecting; doubled after each failed attempt.
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// `--unix-socket-sink`: writes each accepted submission as one JSON line to
+/// a Unix domain socket, for a co-located consumer.
+///
+/// A background task owns the connection, so submissions never wait on the
+/// socket. While the consumer is absent or reads slower than submissions
+/// arrive, lines queue in memory, up to `--unix-socket-buffer` of them,
+/// while the task reconnects with backoff; a line that failed to write is
+/// retried on the next connection. Once the queue is full, further
+/// submissions are not forwarded until it drains. Delivery is best effort:
+/// queued lines are lost if the server stops, and a line written just as the
+/// consumer goes away may be lost too.
+#[derive(Clone)]
+pub struct SocketSink {
+    lines: mpsc::Sender<String>,
+}
+
+impl SocketSink {
+    /// Starts the writer task; the socket is first connected on demand.
+    pub fn spawn(path: PathBuf, buffer: usize) -> Self {
+        let (lines, queued) = mpsc::channel(buffer);
+        actix_web::rt::spawn(write_lines(path, queued));
+        SocketSink { lines }
+    }
+
+    /// Queues `submission` without waiting; false if the queue is full.
+    pub fn send(&self, submission: &serde_json::Value) -> bool {
+        match self.lines.try_send(format!("{}\n", submission)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) | Err(TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+async fn write_lines(path: PathBuf, mut queued: mpsc::Receiver<String>) {
+    let mut stream: Option<UnixStream> = None;
+    let mut backoff = MIN_BACKOFF;
+
+    while let Some(line) = queued.recv().await {
+        loop {
+            let connected = match stream.as_mut() {
+                Some(connected) => connected,
+                None => match UnixStream::connect(&path).await {
+                    Ok(connected) => {
+                        if backoff > MIN_BACKOFF {
+                            eprintln!("Unix socket sink {} reconnected", path.display());
+                        }
+                        backoff = MIN_BACKOFF;
+                        stream.insert(connected)
+                    }
+                    Err(e) => {
+                        if backoff == MIN_BACKOFF {
+                            eprintln!(
+                                "Unix socket sink {} unavailable, retrying: {}",
+                                path.display(),
+                                e
+                            );
+                        }
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                        continue;
+                    }
+                },
+            };
+
+            match connected.write_all(line.as_bytes()).await {
+                Ok(()) => break,
+                Err(e) => {
+                    eprintln!("Unix socket sink {} write error: {}", path.display(), e);
+                    stream = None;
+                    tokio::time::sleep(MIN_BACKOFF).await;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::{too_much_uppercase, ContactForm, UppercaseAction, ValidationConfig};
+
+/// The individual anti-spam signals for one submission and the verdict they
+/// add up to. Stored as JSON with the submission so operators can see which
+/// signals fire on real traffic before tightening thresholds.
+#[derive(Serialize)]
+pub struct Signals {
+    /// The hidden `_gotcha` field, invisible to people, was filled in.
+    pub honeypot_filled: bool,
+    /// Seconds between `_rendered_at` and submission, when the form sent it.
+    pub fill_secs: Option<i64>,
+    /// Submitted faster than `--spam-min-fill-secs` after rendering.
+    pub too_fast: bool,
+    pub links: usize,
+    /// More links than `--max-links`.
+    pub too_many_links: bool,
+    /// More capitals than `--max-uppercase-ratio`, with
+    /// `--uppercase-action flag`.
+    pub too_much_uppercase: bool,
+    pub verdict: Verdict,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Clean,
+    Suspicious,
+    Spam,
+}
+
+impl Verdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Verdict::Clean => "clean",
+            Verdict::Suspicious => "suspicious",
+            Verdict::Spam => "spam",
+        }
+    }
+}
+
+impl Signals {
+    pub fn fired(&self) -> Vec<&'static str> {
+        [
+            (self.honeypot_filled, "honeypot_filled"),
+            (self.too_fast, "too_fast"),
+            (self.too_many_links, "too_many_links"),
+            (self.too_much_uppercase, "too_much_uppercase"),
+        ]
+        .into_iter()
+        .filter_map(|(fired, name)| fired.then_some(name))
+        .collect()
+    }
+}
+
+/// A filled honeypot is conclusive on its own; otherwise one signal is only
+/// suspicious and two or more make a submission spam.
+pub fn evaluate(form: &ContactForm, config: &ValidationConfig, now: OffsetDateTime) -> Signals {
+    let honeypot_filled = form
+        .honeypot
+        .as_deref()
+        .is_some_and(|value| !value.trim().is_empty());
+
+    let fill_secs = form
+        .rendered_at
+        .as_ref()
+        .and_then(|rendered_at| rendered_at.typed())
+        .map(|rendered_at| now.unix_timestamp() - rendered_at);
+    let too_fast = match (fill_secs, config.spam_min_fill_secs) {
+        (Some(secs), Some(min)) => secs < min as i64,
+        _ => false,
+    };
+
+    let links = config.link_regex.find_iter(&form.message).count();
+    let too_many_links = config.max_links.is_some_and(|max| links > max);
+    let too_much_uppercase = config.uppercase_action == UppercaseAction::Flag
+        && too_much_uppercase(&form.message, config).is_some();
+
+    let mut signals = Signals {
+        honeypot_filled,
+        fill_secs,
+        too_fast,
+        links,
+        too_many_links,
+        too_much_uppercase,
+        verdict: Verdict::Clean,
+    };
+    signals.verdict = match signals.fired().len() {
+        _ if honeypot_filled => Verdict::Spam,
+        0 => Verdict::Clean,
+        1 => Verdict::Suspicious,
+        _ => Verdict::Spam,
+    };
+    signals
+}
@@ -0,0 +1,159 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Why a spooled line was not stored.
+pub enum Replay {
+    /// The database is still failing; the line and those after it wait for
+    /// the next attempt.
+    Failed(String),
+    /// The line can never be stored; it is moved to the `.rejected` file
+    /// next to the spool so it does not hold up the rest.
+    Rejected(String),
+}
+
+/// `--spool-file`: keeps submissions the database failed to store, one JSON
+/// line each, until a background thread manages to replay them.
+///
+/// Lines are synced to disk before the submission is acknowledged, and
+/// replayed in the order they were spooled. A line is removed once stored,
+/// so a submission is stored twice only if the server stops between storing
+/// it and rewriting the spool.
+pub struct Spool {
+    path: PathBuf,
+    /// Held while appending or rewriting, never while replaying.
+    file: Mutex<()>,
+    pending: AtomicUsize,
+}
+
+impl Spool {
+    /// Opens the spool, counting the lines left by a previous run.
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let pending = read_lines(&path)?.len();
+        if pending > 0 {
+            eprintln!(
+                "Spool {} holds {} submissions, replaying",
+                path.display(),
+                pending
+            );
+        }
+        Ok(Spool {
+            path,
+            file: Mutex::new(()),
+            pending: AtomicUsize::new(pending),
+        })
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    pub fn append(&self, entry: &serde_json::Value) -> io::Result<()> {
+        let _file = self.file.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", entry)?;
+        file.sync_data()?;
+        self.pending.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Replays the spool every `interval` on a background thread, passing
+    /// each line to `store` until one fails.
+    pub fn spawn_replay<F>(self: Arc<Self>, interval: Duration, mut store: F)
+    where
+        F: FnMut(&str) -> Result<(), Replay> + Send + 'static,
+    {
+        thread::spawn(move || loop {
+            match self.replay_once(&mut store) {
+                Ok(0) => {}
+                Ok(stored) => println!(
+                    "{}",
+                    serde_json::json!({
+                        "event": "spool_replayed",
+                        "stored": stored,
+                        "pending": self.pending(),
+                    })
+                ),
+                Err(e) => eprintln!("Spool {} error: {}", self.path.display(), e),
+            }
+            thread::sleep(interval);
+        });
+    }
+
+    /// The number of lines taken off the spool, stored or rejected.
+    pub fn replay_once<F>(&self, store: &mut F) -> io::Result<usize>
+    where
+        F: FnMut(&str) -> Result<(), Replay>,
+    {
+        if self.pending() == 0 {
+            return Ok(0);
+        }
+        let lines = {
+            let _file = self.file.lock().unwrap();
+            read_lines(&self.path)?
+        };
+
+        let mut done = 0;
+        let mut rejected = Vec::new();
+        for line in &lines {
+            match store(line) {
+                Ok(()) => {}
+                Err(Replay::Rejected(e)) => {
+                    eprintln!("Spooled submission rejected: {}", e);
+                    rejected.push(line.as_str());
+                }
+                Err(Replay::Failed(e)) => {
+                    eprintln!("Spool replay failed, retrying later: {}", e);
+                    break;
+                }
+            }
+            done += 1;
+        }
+        if done == 0 {
+            return Ok(0);
+        }
+
+        if !rejected.is_empty() {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path.with_extension("rejected"))?;
+            for line in rejected {
+                writeln!(file, "{}", line)?;
+            }
+            file.sync_data()?;
+        }
+
+        // Lines are only ever appended meanwhile, so the replayed ones are
+        // still the first `done`.
+        let _file = self.file.lock().unwrap();
+        let rest: Vec<String> = read_lines(&self.path)?.into_iter().skip(done).collect();
+        let temporary = self.path.with_extension("tmp");
+        let mut file = File::create(&temporary)?;
+        for line in &rest {
+            writeln!(file, "{}", line)?;
+        }
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        self.pending.store(rest.len(), Ordering::Relaxed);
+        Ok(done)
+    }
+}
+
+fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    match File::open(path) {
+        Ok(file) => BufReader::new(file)
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
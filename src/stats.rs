@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+
+use crate::request_id;
+use crate::respond::ErrorCategory;
+
+/// In-process submission counters, shared by all workers.
+pub struct Stats {
+    started: Instant,
+    accepted: AtomicU64,
+    rejected: Mutex<BTreeMap<&'static str, u64>>,
+    rejected_by_category: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats {
+            started: Instant::now(),
+            accepted: AtomicU64::new(0),
+            rejected: Mutex::new(BTreeMap::new()),
+            rejected_by_category: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn record(&self, status: StatusCode, category: ErrorCategory) {
+        if status.is_success() {
+            self.accepted.fetch_add(1, Ordering::Relaxed);
+        } else {
+            *self
+                .rejected
+                .lock()
+                .unwrap()
+                .entry(rejection_reason(status))
+                .or_default() += 1;
+            *self
+                .rejected_by_category
+                .lock()
+                .unwrap()
+                .entry(category.as_str())
+                .or_default() += 1;
+        }
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        let rejected = self.rejected.lock().unwrap();
+        serde_json::json!({
+            "uptime_secs": self.started.elapsed().as_secs(),
+            "accepted": self.accepted.load(Ordering::Relaxed),
+            "rejected": rejected.values().sum::<u64>(),
+            "rejected_by_reason": *rejected,
+            "rejected_by_category": *self.rejected_by_category.lock().unwrap(),
+        })
+    }
+}
+
+fn rejection_reason(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "invalid",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::CONFLICT => "duplicate",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        StatusCode::SERVICE_UNAVAILABLE => "unavailable",
+        status if status.is_server_error() => "server_error",
+        _ => "other",
+    }
+}
+
+/// Counts every submission outcome, including rejections produced by the
+/// rate limiter before the handler runs, and logs a `rejected` event with its
+/// category for each one that fails.
+pub async fn middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let response = next.call(req).await?;
+    let status = response.status();
+    let category = ErrorCategory::of(response.response());
+
+    if let Some(stats) = response.request().app_data::<web::Data<Stats>>() {
+        stats.record(status, category);
+    }
+    if !status.is_success() {
+        eprintln!(
+            "[{}] {}",
+            request_id::get(response.request()),
+            serde_json::json!({
+                "event": "rejected",
+                "category": category.as_str(),
+                "reason": rejection_reason(status),
+                "status": status.as_u16(),
+            })
+        );
+    }
+    Ok(response)
+}
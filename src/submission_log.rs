@@ -0,0 +1,109 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::ContactForm;
+
+/// Human-readable, size-rotated log of accepted submissions, or of rejected
+/// raw bodies with `--store-raw-body`.
+///
+/// Once the active file would grow past `max_bytes` it is renamed to
+/// `<path>.1`, older files shift up by one, and anything beyond
+/// `max_files` is removed.
+pub struct SubmissionLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Mutex<ActiveFile>,
+}
+
+struct ActiveFile {
+    file: File,
+    len: u64,
+}
+
+impl SubmissionLog {
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = open_append(path)?;
+        let len = file.metadata()?.len();
+        Ok(SubmissionLog {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            file: Mutex::new(ActiveFile { file, len }),
+        })
+    }
+
+    pub fn append(&self, form: &ContactForm) -> io::Result<()> {
+        self.write_entry(&format_entry(form))
+    }
+
+    /// Records a rejected request body together with why it was rejected.
+    pub fn append_rejection(&self, status: u16, reason: &str, body: &str) -> io::Result<()> {
+        self.write_entry(&format!(
+            "[{}] {}\nReason: {}\nBody:\n{}\n----------------------------------------\n",
+            timestamp(),
+            status,
+            reason,
+            body
+        ))
+    }
+
+    fn write_entry(&self, entry: &str) -> io::Result<()> {
+        let mut active = self.file.lock().unwrap();
+
+        if active.len > 0 && active.len + entry.len() as u64 > self.max_bytes {
+            self.rotate()?;
+            *active = ActiveFile {
+                file: open_append(&self.path)?,
+                len: 0,
+            };
+        }
+
+        active.file.write_all(entry.as_bytes())?;
+        active.len += entry.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+
+        let _ = fs::remove_file(self.rotated_path(self.max_files));
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn timestamp() -> String {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_default()
+}
+
+fn format_entry(form: &ContactForm) -> String {
+    format!(
+        "[{}]\nName:    {}\nEmail:   {}\nSubject: {}\nMessage:\n{}\n----------------------------------------\n",
+        timestamp(), form.name, form.email, form.subject, form.message
+    )
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Clients tracked at once; the map is cleared when it fills up rather than
+/// growing without bound under a spoofed-address flood.
+const MAX_TRACKED: usize = 10_000;
+
+/// Progressive delay for clients whose submissions keep getting rejected.
+///
+/// The delay starts at `step` after the first rejection, doubles with each
+/// further one up to `max`, and resets after an accepted submission.
+pub struct Tarpit {
+    step: Duration,
+    max: Duration,
+    failures: Mutex<HashMap<IpAddr, u32>>,
+}
+
+impl Tarpit {
+    pub fn new(step: Duration, max: Duration) -> Self {
+        Tarpit {
+            step,
+            max,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn delay(&self, ip: IpAddr) -> Duration {
+        match self.failures.lock().unwrap().get(&ip) {
+            Some(&failures) if failures > 0 => self
+                .step
+                .saturating_mul(1 << (failures - 1).min(16))
+                .min(self.max),
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn record_failure(&self, ip: IpAddr) {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() >= MAX_TRACKED && !failures.contains_key(&ip) {
+            failures.clear();
+        }
+        *failures.entry(ip).or_default() += 1;
+    }
+
+    pub fn record_success(&self, ip: IpAddr) {
+        self.failures.lock().unwrap().remove(&ip);
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, SupportedCipherSuite, SupportedProtocolVersion};
+
+static TLS13_ONLY: [&SupportedProtocolVersion; 1] = [&rustls::version::TLS13];
+
+/// Oldest protocol `--tls-min-version` accepts. rustls has no TLS 1.0 or 1.1
+/// support at all, so those can never be negotiated.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum TlsVersion {
+    #[value(name = "1.2")]
+    Tls12,
+    #[value(name = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    fn enabled(self) -> &'static [&'static SupportedProtocolVersion] {
+        match self {
+            TlsVersion::Tls12 => rustls::ALL_VERSIONS,
+            TlsVersion::Tls13 => &TLS13_ONLY,
+        }
+    }
+}
+
+fn suite_name(suite: &SupportedCipherSuite) -> String {
+    format!("{:?}", suite.suite())
+}
+
+/// Builds the server config for `--tls-cert`/`--tls-key`. `ciphers` narrows
+/// the provider's suites (all AEAD, none of them CBC or RC4) by IANA name,
+/// e.g. `TLS13_AES_256_GCM_SHA384`; unknown names, and lists that leave no
+/// suite for an enabled protocol version, are refused.
+pub fn server_config(
+    cert: &Path,
+    key: &Path,
+    min_version: TlsVersion,
+    ciphers: &[String],
+) -> Result<ServerConfig, String> {
+    let mut provider = ring::default_provider();
+    if !ciphers.is_empty() {
+        let known: Vec<String> = provider.cipher_suites.iter().map(suite_name).collect();
+        if let Some(unknown) = ciphers.iter().find(|name| !known.contains(name)) {
+            return Err(format!(
+                "--tls-ciphers: unsupported cipher suite {}; expected one of {}",
+                unknown,
+                known.join(", ")
+            ));
+        }
+        provider
+            .cipher_suites
+            .retain(|suite| ciphers.contains(&suite_name(suite)));
+    }
+
+    let versions = min_version.enabled();
+    for version in versions {
+        if !provider
+            .cipher_suites
+            .iter()
+            .any(|suite| suite.version() == *version)
+        {
+            return Err(format!(
+                "--tls-ciphers: no cipher suite left for {:?}; allow one or change --tls-min-version",
+                version.version
+            ));
+        }
+    }
+
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("--tls-cert {}: {}", cert.display(), e))?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .map_err(|e| format!("--tls-key {}: {}", key.display(), e))?;
+
+    ServerConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(versions)
+        .map_err(|e| e.to_string())?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("--tls-cert/--tls-key: {}", e))
+}
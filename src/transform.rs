@@ -0,0 +1,94 @@
+use crate::{ContactForm, FORM_FIELDS};
+
+type Transform = fn(&str) -> String;
+
+/// Built-in transforms available to `--transform`, by name.
+const REGISTRY: [(&str, Transform); 5] = [
+    ("trim", |value| value.trim().to_string()),
+    ("lowercase", |value| value.to_lowercase()),
+    ("uppercase", |value| value.to_uppercase()),
+    ("collapse_whitespace", collapse_whitespace),
+    ("title_case", title_case),
+];
+
+/// The ordered transforms for one field, parsed from `field=name,name,...`.
+#[derive(Clone, Debug)]
+pub struct FieldTransforms {
+    field: &'static str,
+    steps: Vec<Transform>,
+}
+
+/// Normalizes submitted fields before validation and storage, so length and
+/// format checks see the transformed values.
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    fields: Vec<FieldTransforms>,
+}
+
+impl Pipeline {
+    pub fn new(fields: Vec<FieldTransforms>) -> Self {
+        Pipeline { fields }
+    }
+
+    pub fn apply(&self, form: &mut ContactForm) {
+        for transforms in &self.fields {
+            if let Some(value) = form.field_mut(transforms.field) {
+                for step in &transforms.steps {
+                    *value = step(value);
+                }
+            }
+        }
+    }
+}
+
+pub fn parse(spec: &str) -> Result<FieldTransforms, String> {
+    let (field, names) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected field=transform[,transform...], got {}", spec))?;
+    let field = FORM_FIELDS
+        .iter()
+        .map(|(name, _)| *name)
+        .find(|name| *name == field.trim())
+        .ok_or_else(|| format!("unknown field: {}", field))?;
+
+    let steps = names
+        .split(',')
+        .map(|name| {
+            REGISTRY
+                .iter()
+                .find(|(known, _)| *known == name.trim())
+                .map(|(_, transform)| *transform)
+                .ok_or_else(|| {
+                    let known: Vec<_> = REGISTRY.iter().map(|(known, _)| *known).collect();
+                    format!(
+                        "unknown transform {}; expected one of {}",
+                        name,
+                        known.join(", ")
+                    )
+                })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(FieldTransforms { field, steps })
+}
+
+fn collapse_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Uppercases the first letter of each whitespace-separated word and
+/// lowercases the rest, keeping the original spacing.
+fn title_case(value: &str) -> String {
+    let mut at_word_start = true;
+    value
+        .chars()
+        .flat_map(|c| {
+            let cased: Vec<char> = if at_word_start {
+                c.to_uppercase().collect()
+            } else {
+                c.to_lowercase().collect()
+            };
+            at_word_start = c.is_whitespace();
+            cased
+        })
+        .collect()
+}
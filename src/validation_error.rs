@@ -0,0 +1,295 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+use unicode_script::Script;
+
+use crate::FORM_FIELDS;
+
+/// Which name check rejected a submission, reported so checks can be tuned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameHeuristic {
+    /// Matched a `--disallow-name-pattern`.
+    Pattern,
+    Link,
+    Digits,
+    NoVowels,
+}
+
+impl NameHeuristic {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NameHeuristic::Pattern => "pattern",
+            NameHeuristic::Link => "link",
+            NameHeuristic::Digits => "digits",
+            NameHeuristic::NoVowels => "no_vowels",
+        }
+    }
+}
+
+/// Why a submission failed validation. [`ValidationError::code`] together
+/// with [`ValidationError::field`] is the stable contract for frontends; the
+/// `Display` message is for people and may be reworded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    Required {
+        field: &'static str,
+    },
+    TooLong {
+        field: &'static str,
+        limit: usize,
+        actual: usize,
+    },
+    TooShort {
+        field: &'static str,
+        limit: usize,
+        actual: usize,
+    },
+    TooManyWords {
+        field: &'static str,
+        limit: usize,
+        actual: usize,
+    },
+    NameDisallowed {
+        heuristic: NameHeuristic,
+    },
+    EmailInvalid,
+    EmailDomainNotAllowed,
+    EmailNoMx,
+    DisallowedScript {
+        field: &'static str,
+        script: Script,
+    },
+    TooManyLinks {
+        limit: usize,
+        actual: usize,
+    },
+    TooManyNewlines {
+        limit: usize,
+        actual: usize,
+    },
+    /// `limit` and `actual` are percentages of the message's letters.
+    TooMuchUppercase {
+        limit: usize,
+        actual: usize,
+    },
+    LocaleInvalid,
+    RenderedAtNotNumber,
+    UnknownSourcePage,
+    WebsiteInvalid,
+    WebsiteSchemeNotAllowed {
+        https_only: bool,
+    },
+    AttachmentsDisabled,
+    AttachmentNotBase64,
+    AttachmentTooLarge {
+        limit: usize,
+        actual: usize,
+    },
+    AttachmentTypeNotAllowed {
+        allowed: Vec<String>,
+    },
+}
+
+impl ValidationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValidationError::Required { .. } => "required",
+            ValidationError::TooLong { .. } => "too_long",
+            ValidationError::TooShort { .. } => "too_short",
+            ValidationError::TooManyWords { .. } => "too_many_words",
+            ValidationError::NameDisallowed { .. } => "disallowed_name",
+            ValidationError::EmailInvalid
+            | ValidationError::LocaleInvalid
+            | ValidationError::WebsiteInvalid
+            | ValidationError::AttachmentNotBase64 => "invalid_format",
+            ValidationError::EmailDomainNotAllowed => "domain_not_allowed",
+            ValidationError::EmailNoMx => "no_mx",
+            ValidationError::DisallowedScript { .. } => "disallowed_script",
+            ValidationError::TooManyLinks { .. } => "too_many_links",
+            ValidationError::TooManyNewlines { .. } => "too_many_newlines",
+            ValidationError::TooMuchUppercase { .. } => "too_much_uppercase",
+            ValidationError::RenderedAtNotNumber => "invalid_type",
+            ValidationError::UnknownSourcePage => "unknown_source_page",
+            ValidationError::WebsiteSchemeNotAllowed { .. } => "disallowed_scheme",
+            ValidationError::AttachmentsDisabled => "attachments_disabled",
+            ValidationError::AttachmentTooLarge { .. } => "too_large",
+            ValidationError::AttachmentTypeNotAllowed { .. } => "disallowed_type",
+        }
+    }
+
+    /// The submitted field the error is about.
+    pub fn field(&self) -> &'static str {
+        match self {
+            ValidationError::Required { field }
+            | ValidationError::TooLong { field, .. }
+            | ValidationError::TooShort { field, .. }
+            | ValidationError::TooManyWords { field, .. }
+            | ValidationError::DisallowedScript { field, .. } => field,
+            ValidationError::NameDisallowed { .. } => "name",
+            ValidationError::EmailInvalid
+            | ValidationError::EmailDomainNotAllowed
+            | ValidationError::EmailNoMx => "email",
+            ValidationError::TooManyLinks { .. }
+            | ValidationError::TooManyNewlines { .. }
+            | ValidationError::TooMuchUppercase { .. } => "message",
+            ValidationError::LocaleInvalid => "locale",
+            ValidationError::RenderedAtNotNumber => "_rendered_at",
+            ValidationError::UnknownSourcePage => "source_page",
+            ValidationError::WebsiteInvalid | ValidationError::WebsiteSchemeNotAllowed { .. } => {
+                "website"
+            }
+            ValidationError::AttachmentsDisabled
+            | ValidationError::AttachmentNotBase64
+            | ValidationError::AttachmentTooLarge { .. }
+            | ValidationError::AttachmentTypeNotAllowed { .. } => "attachment",
+        }
+    }
+
+    /// The configured limit and the submitted amount, for errors about sizes.
+    pub fn limit(&self) -> Option<(usize, usize)> {
+        match self {
+            ValidationError::TooLong { limit, actual, .. }
+            | ValidationError::TooShort { limit, actual, .. }
+            | ValidationError::TooManyWords { limit, actual, .. }
+            | ValidationError::TooManyLinks { limit, actual }
+            | ValidationError::TooManyNewlines { limit, actual }
+            | ValidationError::TooMuchUppercase { limit, actual }
+            | ValidationError::AttachmentTooLarge { limit, actual } => Some((*limit, *actual)),
+            _ => None,
+        }
+    }
+}
+
+fn label(field: &str) -> &str {
+    FORM_FIELDS
+        .iter()
+        .find(|(name, _)| *name == field)
+        .map_or(field, |(_, label)| label)
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Required { field } => write!(f, "{} cannot be empty", label(field)),
+            ValidationError::TooLong {
+                field,
+                limit,
+                actual,
+            } => write!(
+                f,
+                "{} must be {} characters or less (got {})",
+                label(field),
+                limit,
+                actual
+            ),
+            ValidationError::TooShort {
+                field,
+                limit,
+                actual,
+            } => write!(
+                f,
+                "{} must be at least {} characters (got {})",
+                label(field),
+                limit,
+                actual
+            ),
+            ValidationError::TooManyWords {
+                field,
+                limit,
+                actual,
+            } => write!(
+                f,
+                "{} must be {} words or less (got {})",
+                label(field),
+                limit,
+                actual
+            ),
+            ValidationError::NameDisallowed { heuristic } => match heuristic {
+                NameHeuristic::Pattern => write!(f, "Name is not allowed"),
+                NameHeuristic::Link => write!(f, "Name must not contain a link"),
+                NameHeuristic::Digits => write!(f, "Name contains too many digits"),
+                NameHeuristic::NoVowels => write!(f, "Name does not look like a name"),
+            },
+            ValidationError::EmailInvalid => write!(f, "Invalid email format"),
+            ValidationError::EmailDomainNotAllowed => write!(f, "Email domain is not allowed"),
+            ValidationError::EmailNoMx => write!(f, "Email domain cannot receive mail"),
+            ValidationError::DisallowedScript { field, script } => write!(
+                f,
+                "{} contains unsupported {} characters",
+                label(field),
+                script.full_name()
+            ),
+            ValidationError::TooManyLinks { limit, actual } => write!(
+                f,
+                "Message may contain at most {} links (got {})",
+                limit, actual
+            ),
+            ValidationError::TooManyNewlines { limit, actual } => write!(
+                f,
+                "Message may contain at most {} line breaks (got {})",
+                limit, actual
+            ),
+            ValidationError::TooMuchUppercase { limit, actual } => write!(
+                f,
+                "Message may be at most {}% capital letters (got {}%)",
+                limit, actual
+            ),
+            ValidationError::LocaleInvalid => write!(f, "Invalid locale format"),
+            ValidationError::RenderedAtNotNumber => write!(f, "_rendered_at must be a number"),
+            ValidationError::UnknownSourcePage => write!(f, "Source page is not a known page"),
+            ValidationError::WebsiteInvalid => write!(f, "Website must be a valid web address"),
+            ValidationError::WebsiteSchemeNotAllowed { https_only: true } => {
+                write!(f, "Website must be an https:// address")
+            }
+            ValidationError::WebsiteSchemeNotAllowed { https_only: false } => {
+                write!(f, "Website must be an http:// or https:// address")
+            }
+            ValidationError::AttachmentsDisabled => write!(f, "Attachments are not accepted"),
+            ValidationError::AttachmentNotBase64 => write!(f, "Attachment must be base64 encoded"),
+            ValidationError::AttachmentTooLarge { limit, actual } => write!(
+                f,
+                "Attachment must be {} bytes or less (got {})",
+                limit, actual
+            ),
+            ValidationError::AttachmentTypeNotAllowed { allowed } => {
+                write!(f, "Attachment type must be one of: {}", allowed.join(", "))
+            }
+        }
+    }
+}
+
+/// The 400 response body: `code` and `message`, `field`, `limit`/`actual`
+/// for size errors and `heuristic` for rejected names. `error` repeats the
+/// message for clients written before `message` was added.
+impl Serialize for ValidationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Body {
+            error: String,
+            code: &'static str,
+            message: String,
+            field: &'static str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            limit: Option<usize>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            actual: Option<usize>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            heuristic: Option<&'static str>,
+        }
+
+        let limit = self.limit();
+        Body {
+            error: self.to_string(),
+            code: self.code(),
+            message: self.to_string(),
+            field: self.field(),
+            limit: limit.map(|(limit, _)| limit),
+            actual: limit.map(|(_, actual)| actual),
+            heuristic: match self {
+                ValidationError::NameDisallowed { heuristic } => Some(heuristic.as_str()),
+                _ => None,
+            },
+        }
+        .serialize(serializer)
+    }
+}
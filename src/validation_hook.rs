@@ -0,0 +1,70 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// External program that gets the final say on a submission.
+///
+/// The program receives the submission as JSON on stdin. Exit status 0
+/// accepts it; any other status rejects it, and stderr becomes the error
+/// message returned to the client. The program runs with the server's
+/// privileges and sees unsanitised user input, so it must treat stdin as
+/// hostile and should not be writable by anyone but the operator.
+pub struct ValidationHook {
+    program: PathBuf,
+    timeout: Duration,
+}
+
+pub enum HookError {
+    Rejected(String),
+    TimedOut,
+    Failed(io::Error),
+}
+
+impl ValidationHook {
+    pub fn new(program: PathBuf, timeout: Duration) -> Self {
+        ValidationHook { program, timeout }
+    }
+
+    pub async fn run(&self, input: &[u8]) -> Result<(), HookError> {
+        match tokio::time::timeout(self.timeout, self.execute(input)).await {
+            Ok(result) => result,
+            Err(_) => Err(HookError::TimedOut),
+        }
+    }
+
+    async fn execute(&self, input: &[u8]) -> Result<(), HookError> {
+        let mut child = Command::new(&self.program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(HookError::Failed)?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // A program may decide without reading all of stdin.
+            match stdin.write_all(input).await {
+                Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                    return Err(HookError::Failed(e))
+                }
+                _ => {}
+            }
+        }
+
+        let output = child.wait_with_output().await.map_err(HookError::Failed)?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            Err(HookError::Rejected(if stderr.is_empty() {
+                "Submission rejected".to_string()
+            } else {
+                stderr
+            }))
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use awc::Client;
+use handlebars::Handlebars;
+use serde_json::Value;
+use tokio::sync::Notify;
+
+use crate::ContactForm;
+
+const TEMPLATE: &str = "payload";
+
+/// `--webhook-template`, compiled once at startup and shared by every worker.
+///
+/// Values are JSON-escaped rather than HTML-escaped when substituted, so
+/// `{"text": "From {{name}}: {{message}}"}` stays valid JSON whatever the
+/// submission contains. Use `{{{json this}}}` to embed the whole submission.
+#[derive(Clone)]
+pub struct PayloadTemplate {
+    registry: Arc<Handlebars<'static>>,
+}
+
+impl PayloadTemplate {
+    /// Loads and compiles the template, then renders it for a sample
+    /// submission to make sure the result is JSON.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(true);
+        registry.register_escape_fn(|value| {
+            let quoted = Value::String(value.to_string()).to_string();
+            quoted[1..quoted.len() - 1].to_string()
+        });
+        registry.register_helper(
+            "json",
+            Box::new(
+                |helper: &handlebars::Helper,
+                 _: &Handlebars,
+                 _: &handlebars::Context,
+                 _: &mut handlebars::RenderContext,
+                 out: &mut dyn handlebars::Output|
+                 -> handlebars::HelperResult {
+                    let value = helper.param(0).map(|param| param.value().to_string());
+                    out.write(&value.unwrap_or_default())?;
+                    Ok(())
+                },
+            ),
+        );
+        registry
+            .register_template_string(TEMPLATE, source)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let template = PayloadTemplate {
+            registry: Arc::new(registry),
+        };
+        let sample = ContactForm {
+            name: "Sample \"name\"".to_string(),
+            email: "sample@example.com".to_string(),
+            subject: "Sample subject".to_string(),
+            message: "Sample\nmessage".to_string(),
+            locale: Some("en".to_string()),
+            source_page: Some("/contact".to_string()),
+            ..Default::default()
+        };
+        let rendered = template
+            .render(&context(0, Some("example.com"), &sample))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_str::<Value>(&rendered).map_err(|e| {
+            format!(
+                "{}: does not render to JSON ({}): {}",
+                path.display(),
+                e,
+                rendered
+            )
+        })?;
+        Ok(template)
+    }
+
+    fn render(&self, context: &Value) -> Result<String, String> {
+        self.registry
+            .render(TEMPLATE, context)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// The variables a template sees: the stored submission fields plus its `id`
+/// and `site`. Without a template this is the payload itself.
+pub fn context(id: i64, site: Option<&str>, form: &ContactForm) -> Value {
+    let mut context = serde_json::to_value(form).unwrap_or_default();
+    if let Some(object) = context.as_object_mut() {
+        object.insert("id".to_string(), id.into());
+        object.insert("site".to_string(), site.into());
+    }
+    context
+}
+
+/// The HTTP client for webhook deliveries. A request that takes longer than
+/// `timeout` (`--webhook-timeout-ms`) fails like any other.
+pub fn client(timeout: Duration) -> Client {
+    Client::builder().timeout(timeout).finish()
+}
+
+/// `--webhook-url`: POSTs each accepted submission as JSON. Built per
+/// worker, since the HTTP client is not `Send`; the worker's requests share
+/// its client and so its pooled connections.
+#[derive(Clone)]
+pub struct Webhook {
+    client: Client,
+    url: String,
+    template: Option<PayloadTemplate>,
+}
+
+impl Webhook {
+    pub fn new(url: String, template: Option<PayloadTemplate>, timeout: Duration) -> Self {
+        Webhook {
+            client: client(timeout),
+            url,
+            template,
+        }
+    }
+
+    pub async fn send(
+        &self,
+        id: i64,
+        site: Option<&str>,
+        form: &ContactForm,
+    ) -> Result<(), String> {
+        let context = context(id, site, form);
+        let body = match &self.template {
+            Some(template) => template.render(&context)?,
+            None => context.to_string(),
+        };
+
+        post(&self.client, &self.url, body).await
+    }
+}
+
+async fn post(client: &Client, url: &str, body: String) -> Result<(), String> {
+    let response = client
+        .post(url)
+        .content_type("application/json")
+        .send_body(body)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("webhook returned {}", response.status()));
+    }
+    Ok(())
+}
+
+/// `--webhook-batch-size`: queues submissions and POSTs them as a JSON array
+/// of up to `size` payloads, once `size` are waiting or every interval,
+/// whichever comes first. Batches go out in submission order; a batch that
+/// fails stays at the front of the queue and is retried whole on the next
+/// interval. At most `capacity` payloads wait (`--webhook-batch-buffer`);
+/// once that many are queued, new submissions are not forwarded.
+pub struct WebhookBatch {
+    url: String,
+    template: Option<PayloadTemplate>,
+    size: usize,
+    capacity: usize,
+    timeout: Duration,
+    queue: Mutex<VecDeque<Value>>,
+    full: Notify,
+}
+
+impl WebhookBatch {
+    pub fn new(
+        url: String,
+        template: Option<PayloadTemplate>,
+        size: usize,
+        capacity: usize,
+        timeout: Duration,
+    ) -> Self {
+        WebhookBatch {
+            url,
+            template,
+            size,
+            capacity,
+            timeout,
+            queue: Mutex::new(VecDeque::new()),
+            full: Notify::new(),
+        }
+    }
+
+    /// Queues the submission's payload, rendered now with the template;
+    /// fails if the queue is full.
+    pub fn push(&self, id: i64, site: Option<&str>, form: &ContactForm) -> Result<(), String> {
+        let context = context(id, site, form);
+        let payload = match &self.template {
+            Some(template) => {
+                serde_json::from_str(&template.render(&context)?).map_err(|e| e.to_string())?
+            }
+            None => context,
+        };
+        let mut queue = self.queue.lock().unwrap();
+        if queue.len() >= self.capacity {
+            return Err("Webhook batch queue is full".to_string());
+        }
+        queue.push_back(payload);
+        if queue.len() >= self.size {
+            self.full.notify_one();
+        }
+        Ok(())
+    }
+
+    pub fn spawn(self: Arc<Self>, interval: Duration) {
+        actix_web::rt::spawn(async move {
+            let client = self.client();
+            let mut ticks = tokio::time::interval(interval);
+            ticks.tick().await;
+            let mut failed = false;
+            loop {
+                // After a failure, wait out the interval rather than retrying
+                // on every new submission.
+                if failed {
+                    ticks.tick().await;
+                } else {
+                    tokio::select! {
+                        _ = ticks.tick() => {}
+                        _ = self.full.notified() => {}
+                    }
+                }
+                failed = match self.flush(&client).await {
+                    Ok(()) => false,
+                    Err(e) => {
+                        eprintln!("Webhook batch error: {}", e);
+                        true
+                    }
+                };
+            }
+        });
+    }
+
+    pub fn client(&self) -> Client {
+        client(self.timeout)
+    }
+
+    /// Sends everything queued, one batch at a time; also used on shutdown.
+    pub async fn flush(&self, client: &Client) -> Result<(), String> {
+        loop {
+            let batch: Vec<Value> = {
+                let mut queue = self.queue.lock().unwrap();
+                let take = queue.len().min(self.size);
+                queue.drain(..take).collect()
+            };
+            if batch.is_empty() {
+                return Ok(());
+            }
+
+            let body = serde_json::to_string(&batch).map_err(|e| e.to_string())?;
+            if let Err(e) = post(client, &self.url, body).await {
+                let mut queue = self.queue.lock().unwrap();
+                let newer = mem::take(&mut *queue);
+                queue.extend(batch);
+                queue.extend(newer);
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Answers every request with `200 OK`, passing each body on.
+    fn endpoint() -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (bodies, received) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_ascii_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                reader
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                    .unwrap();
+                bodies.send(String::from_utf8(body).unwrap()).unwrap();
+            }
+        });
+        (url, received)
+    }
+
+    fn queued(batch: &WebhookBatch) -> Vec<i64> {
+        let queue = batch.queue.lock().unwrap();
+        queue
+            .iter()
+            .map(|payload| payload["id"].as_i64().unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn failed_batches_stay_queued_in_order_up_to_the_capacity() {
+        // Nothing listens on a port just released.
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/hook", listener.local_addr().unwrap())
+        };
+        let batch = WebhookBatch::new(url, None, 2, 3, Duration::from_secs(1));
+        for id in 1..=3 {
+            batch.push(id, None, &ContactForm::default()).unwrap();
+        }
+        assert!(batch.push(4, None, &ContactForm::default()).is_err());
+
+        assert!(batch.flush(&batch.client()).await.is_err());
+        assert_eq!(queued(&batch), [1, 2, 3]);
+    }
+
+    #[actix_web::test]
+    async fn queued_payloads_are_sent_on_the_interval() {
+        let (url, received) = endpoint();
+        let batch = Arc::new(WebhookBatch::new(
+            url,
+            None,
+            10,
+            100,
+            Duration::from_secs(1),
+        ));
+        batch.clone().spawn(Duration::from_millis(50));
+        for id in 1..=2 {
+            batch.push(id, None, &ContactForm::default()).unwrap();
+        }
+
+        let mut body = None;
+        for _ in 0..100 {
+            if let Ok(sent) = received.try_recv() {
+                body = Some(sent);
+                break;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+        }
+        let sent: Vec<Value> = serde_json::from_str(&body.expect("no batch was sent")).unwrap();
+        let ids: Vec<_> = sent.iter().map(|payload| payload["id"].as_i64()).collect();
+        assert_eq!(ids, [Some(1), Some(2)]);
+        assert!(queued(&batch).is_empty());
+    }
+}